@@ -0,0 +1,65 @@
+use std::io::{stdout, Write};
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+
+use crate::commands::items;
+use crate::RenderContext;
+
+/// Reports the items which are missing doc comments,
+/// and the percentage which have them.
+pub fn run(
+    context: &RenderContext,
+    project_path: &Path,
+    docs_path: &Path,
+    all: bool,
+    threshold: Option<f64>,
+) -> Result<()> {
+    let items = items::collect(context, project_path, docs_path, all)?;
+
+    let total = items.len();
+    let documented = items.iter().filter(|item| item.is_documented()).count();
+    let percentage = if total == 0 {
+        100.0
+    } else {
+        documented as f64 / total as f64 * 100.0
+    };
+
+    let mut stdout = stdout().lock();
+
+    let undocumented = items
+        .iter()
+        .filter(|item| !item.is_documented())
+        .collect::<Vec<_>>();
+
+    let width = undocumented
+        .iter()
+        .map(|item| item.location.len())
+        .max()
+        .unwrap_or(0);
+
+    for item in &undocumented {
+        writeln!(
+            stdout,
+            "{:width$}  {:7}  {}",
+            item.location, item.kind, item.path
+        )?;
+    }
+
+    if !undocumented.is_empty() {
+        writeln!(stdout)?;
+    }
+
+    writeln!(
+        stdout,
+        "Coverage: {percentage:.1}% ({documented}/{total} documented)"
+    )?;
+
+    match threshold {
+        Some(threshold) if percentage < threshold => Err(eyre!(
+            "Coverage of {percentage:.1}% is below the threshold of {threshold}%"
+        )),
+        _ => Ok(()),
+    }
+}
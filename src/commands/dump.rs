@@ -0,0 +1,76 @@
+use std::fs;
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+
+use color_eyre::Result;
+use pathdiff::diff_paths;
+use serde::Serialize;
+
+use crate::resolver::Resolver;
+use crate::Info;
+
+/// Version of the dump schema.
+/// This is increased whenever a field is removed or changes meaning.
+/// Adding fields does not change the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Machine-readable output formats.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Pretty-printed JSON.
+    Json,
+}
+
+/// The root of the dump.
+#[derive(Serialize)]
+struct Dump<'a> {
+    schema_version: u32,
+    /// Every module read, ordered by path.
+    modules: Vec<ModuleDump<'a>>,
+}
+
+#[derive(Serialize)]
+struct ModuleDump<'a> {
+    /// Module path, relative to the crate root, for example `config::theme`.
+    /// This is empty for the crate root.
+    path: String,
+    /// Path to the module's source file, relative to the crate root.
+    file: PathBuf,
+    /// Elements in the module, in source order.
+    elements: &'a [Info],
+}
+
+pub fn run(
+    resolver: &Resolver,
+    project_path: &Path,
+    format: DumpFormat,
+    output: Option<&Path>,
+) -> Result<()> {
+    let mut modules = resolver
+        .modules()
+        .iter()
+        .map(|(path, file)| ModuleDump {
+            path: path.to_string(),
+            file: diff_paths(&file.path, project_path).unwrap_or_else(|| file.path.clone()),
+            elements: &file.elements,
+        })
+        .collect::<Vec<_>>();
+
+    modules.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let dump = Dump {
+        schema_version: SCHEMA_VERSION,
+        modules,
+    };
+
+    let str = match format {
+        DumpFormat::Json => serde_json::to_string_pretty(&dump)?,
+    };
+
+    match output {
+        Some(path) => fs::write(path, str)?,
+        None => writeln!(stdout(), "{str}")?,
+    }
+
+    Ok(())
+}
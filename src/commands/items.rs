@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use pathdiff::diff_paths;
+use walkdir::WalkDir;
+
+use crate::renderer::{MarkdownRenderer, OutputFormat, Renderer};
+use crate::replacer::Replacer;
+use crate::resolver::Resolved;
+use crate::{ElementInfo, FieldInfo, Info, RenderContext, SourceLocation, VariantInfo};
+
+/// A single documentable element, field or variant.
+pub struct Item<'a> {
+    /// `type`, `field` or `variant`.
+    pub kind: &'static str,
+    /// Path used to refer to the item in templates.
+    pub path: String,
+    /// Source file and location, for example `src/config.rs:12:5`.
+    pub location: String,
+    pub description: &'a str,
+    /// The element the item belongs to, or the element itself.
+    pub element: &'a Info,
+    /// The field, if the item is one.
+    pub field: Option<&'a FieldInfo>,
+    /// Deepest heading depth the item is rendered at,
+    /// or the default depth when checking every item.
+    pub depth: usize,
+}
+
+impl Item<'_> {
+    pub fn is_documented(&self) -> bool {
+        !self.description.trim().is_empty()
+    }
+}
+
+/// Gets the items to check, ordered by module and then source order.
+///
+/// By default, only the elements, fields and variants injected by the templates are included.
+/// If `all` is set, every element in the crate is included.
+pub fn collect<'a>(
+    context: &'a RenderContext,
+    project_path: &Path,
+    docs_path: &Path,
+    all: bool,
+) -> Result<Vec<Item<'a>>> {
+    let mut selection = Selection::default();
+    if !all {
+        for (resolved, depth) in find_injected(context, docs_path)? {
+            selection.insert(resolved, depth);
+        }
+    }
+
+    let mut modules = context
+        .replace
+        .resolver
+        .modules()
+        .iter()
+        .collect::<Vec<_>>();
+    modules.sort_by_key(|(path, _)| *path);
+
+    let default_depth = all.then_some(context.replace.defaults.depth);
+    let mut items = vec![];
+
+    for (module_path, file) in modules {
+        let file_path = diff_paths(&file.path, project_path).unwrap_or_else(|| file.path.clone());
+        let location = |location: SourceLocation| {
+            format!(
+                "{}:{}:{}",
+                file_path.display(),
+                location.line,
+                location.column
+            )
+        };
+
+        let field_item = |element, field: &'a FieldInfo, parent: &str, depth: usize| Item {
+            kind: "field",
+            path: format!("{parent}.{}", field.name),
+            location: location(field.location),
+            description: &field.description,
+            element,
+            field: Some(field),
+            depth,
+        };
+
+        for info in &file.elements {
+            let element_depth = selection.elements.get(&(info as *const _)).copied();
+            let element_depth = element_depth.or(default_depth);
+            let element_path = module_path.join(&info.ident).to_string();
+
+            if let Some(depth) = element_depth {
+                items.push(Item {
+                    kind: "type",
+                    path: element_path.clone(),
+                    location: location(info.location),
+                    description: &info.description,
+                    element: info,
+                    field: None,
+                    depth,
+                });
+            }
+
+            match &info.element {
+                ElementInfo::Struct(element) => {
+                    // the wrapped field of a newtype is documented as part of the struct itself,
+                    // so is only included if injected on its own
+                    let parent_depth = element_depth.filter(|_| element.newtype_field().is_none());
+
+                    for (field, depth) in selected_fields(&element.fields, &selection, parent_depth)
+                    {
+                        items.push(field_item(info, field, &element_path, depth));
+                    }
+                }
+                ElementInfo::Enum(element) => {
+                    for variant in &element.variants {
+                        // `None` is less than any depth, so this gets the deepest
+                        let variant_depth = element_depth
+                            .map(|depth| depth + 1)
+                            .max(selection.variants.get(&(variant as *const _)).copied());
+                        let variant_path = format!("{element_path}::{}", variant.name);
+
+                        if let Some(depth) = variant_depth {
+                            items.push(Item {
+                                kind: "variant",
+                                path: variant_path.clone(),
+                                location: location(variant.location),
+                                description: &variant.description,
+                                element: info,
+                                field: None,
+                                depth,
+                            });
+                        }
+
+                        for (field, depth) in
+                            selected_fields(&variant.fields, &selection, variant_depth)
+                        {
+                            items.push(field_item(info, field, &variant_path, depth));
+                        }
+                    }
+                }
+                ElementInfo::Alias(_) | ElementInfo::Const(_) | ElementInfo::Static(_) => {}
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// Items selected for the report, compared by address,
+/// with the deepest heading depth each is injected at.
+#[derive(Default)]
+struct Selection {
+    elements: HashMap<*const Info, usize>,
+    fields: HashMap<*const FieldInfo, usize>,
+    variants: HashMap<*const VariantInfo, usize>,
+}
+
+impl Selection {
+    fn insert(&mut self, resolved: Resolved, depth: usize) {
+        fn insert_deepest<T>(map: &mut HashMap<*const T, usize>, item: &T, depth: usize) {
+            let entry = map.entry(item).or_insert(depth);
+            *entry = (*entry).max(depth);
+        }
+
+        match resolved {
+            Resolved::Element(info) => insert_deepest(&mut self.elements, info, depth),
+            Resolved::Field(info) => insert_deepest(&mut self.fields, info, depth),
+            Resolved::Variant(info) => insert_deepest(&mut self.variants, info, depth),
+        }
+    }
+}
+
+/// Renders every template, without writing the output,
+/// to find the items they inject and the depth each is injected at.
+fn find_injected<'a>(
+    context: &'a RenderContext,
+    docs_path: &Path,
+) -> Result<Vec<(Resolved<'a>, usize)>> {
+    if !docs_path.exists() {
+        return Err(eyre!("Documentation path does not exist"));
+    }
+
+    let mut injected = vec![];
+
+    for entry in WalkDir::new(docs_path) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative_path = diff_paths(entry.path(), docs_path).unwrap_or_default();
+        let is_template = docs_path.is_file() || context.templates.is_match(&relative_path);
+
+        if is_template && !context.is_internal(&relative_path) {
+            match context.format {
+                OutputFormat::Markdown => {
+                    let renderer = MarkdownRenderer::new(
+                        String::new(),
+                        context.options,
+                        context.replace.resolver,
+                    );
+                    let mut replacer = Replacer::new(renderer, &context.replace);
+
+                    replacer.replace_file(entry.path())?;
+                    injected.extend_from_slice(replacer.injected());
+                }
+            }
+        }
+    }
+
+    Ok(injected)
+}
+
+/// Gets the fields which are selected,
+/// either directly or because their parent is,
+/// with the deepest depth each is rendered at.
+fn selected_fields<'a>(
+    fields: &'a [FieldInfo],
+    selection: &Selection,
+    parent_depth: Option<usize>,
+) -> Vec<(&'a FieldInfo, usize)> {
+    fields
+        .iter()
+        .filter_map(|field| {
+            let depth = parent_depth
+                .map(|depth| depth + 1)
+                .max(selection.fields.get(&(field as *const _)).copied());
+
+            depth.map(|depth| (field, depth))
+        })
+        .collect()
+}
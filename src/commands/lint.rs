@@ -0,0 +1,248 @@
+use std::io::{stdout, Write};
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use pulldown_cmark::{Event, Parser};
+
+use crate::commands::items::{self, Item};
+use crate::parser::find_intra_doc_links;
+use crate::renderer::{find_description_headings, MAX_HEADING_LEVEL};
+use crate::resolver::Resolver;
+use crate::RenderContext;
+
+/// Rust syntax which is unlikely to mean anything to end-users.
+/// Each is only matched at the start of a word.
+const RUST_SYNTAX: &[&str] = &[
+    "Self", "crate::", "super::", "self::", "&str", "&mut ", "Option<", "Result<", "Vec<", "Box<",
+    "HashMap<", "impl ", "fn ",
+];
+
+/// Code block attributes only understood by rustdoc.
+const RUSTDOC_ATTRIBUTES: &[&str] = &[
+    "ignore",
+    "no_run",
+    "should_panic",
+    "compile_fail",
+    "test_harness",
+    "standalone_crate",
+    "edition2015",
+    "edition2018",
+    "edition2021",
+    "edition2024",
+];
+
+/// Checks the doc comments of items for text which will not work in user-facing docs,
+/// printing a warning for each problem found.
+///
+/// Fails if any warnings are found.
+pub fn run(
+    context: &RenderContext,
+    project_path: &Path,
+    docs_path: &Path,
+    all: bool,
+) -> Result<()> {
+    let items = items::collect(context, project_path, docs_path, all)?;
+    let resolver = context.replace.resolver;
+
+    let warnings = items
+        .iter()
+        .flat_map(|item| {
+            lint_item(item, resolver)
+                .into_iter()
+                .map(move |message| (item, message))
+        })
+        .collect::<Vec<_>>();
+
+    let width = warnings
+        .iter()
+        .map(|(item, _)| item.location.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut stdout = stdout().lock();
+    for (item, message) in &warnings {
+        writeln!(
+            stdout,
+            "{:width$}  {:7}  {}: {message}",
+            item.location, item.kind, item.path
+        )?;
+    }
+
+    if warnings.is_empty() {
+        writeln!(stdout, "No problems found")?;
+        Ok(())
+    } else {
+        writeln!(stdout)?;
+        Err(eyre!("Found {} problem(s) in doc comments", warnings.len()))
+    }
+}
+
+/// Gets a message for each problem with the item's doc comment.
+fn lint_item(item: &Item, resolver: &Resolver) -> Vec<String> {
+    let mut messages = vec![];
+
+    // marker character and length of the open fence
+    let mut fence: Option<(char, usize)> = None;
+
+    for line in prose(item, resolver).lines() {
+        if let Some((marker, len, info)) = parse_fence(line) {
+            match fence {
+                Some((open_marker, open_len))
+                    if marker == open_marker && len >= open_len && info.is_empty() =>
+                {
+                    fence = None;
+                }
+                Some(_) => {}
+                None => {
+                    lint_fence_info(info, &mut messages);
+                    fence = Some((marker, len));
+                }
+            }
+
+            continue;
+        }
+
+        if fence.is_none() {
+            lint_rust_syntax(line, &mut messages);
+        }
+    }
+
+    if fence.is_some() {
+        messages.push("Code block is not closed".to_string());
+    }
+
+    lint_headings(item, &mut messages);
+    lint_links(item, resolver, &mut messages);
+
+    if let Some(field) = item.field {
+        if field.serde_default && field.default.is_none() {
+            messages.push(
+                "Field has a default value which is not documented, \
+                add a `Defaults to ...` sentence or `default` bindocs option"
+                    .to_string(),
+            );
+        }
+    }
+
+    // the same problem can be found on multiple lines
+    let mut unique = vec![];
+    for message in messages {
+        if !unique.contains(&message) {
+            unique.push(message);
+        }
+    }
+
+    unique
+}
+
+/// Gets the item's description with the intra-doc links which can be resolved
+/// and any inline code replaced by spaces, so only the text users will read remains.
+/// Links are rewritten when rendered, and code is expected to contain Rust syntax.
+fn prose(item: &Item, resolver: &Resolver) -> String {
+    let links = find_intra_doc_links(item.description)
+        .into_iter()
+        .filter(|link| {
+            resolver
+                .resolve_link(&link.target, Some(item.element))
+                .is_some()
+        })
+        .map(|link| link.range);
+
+    let code = Parser::new(item.description)
+        .into_offset_iter()
+        .filter_map(|(event, range)| matches!(event, Event::Code(_)).then_some(range));
+
+    let mut bytes = item.description.as_bytes().to_vec();
+    for range in links.chain(code) {
+        // keep line breaks so code blocks are still found line by line
+        for byte in &mut bytes[range] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Parses a line opening or closing a fenced code block
+/// into its marker character, marker length and info string.
+fn parse_fence(line: &str) -> Option<(char, usize, &str)> {
+    let trimmed = line.trim_start();
+    let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = trimmed.chars().take_while(|&c| c == marker).count();
+
+    (len >= 3).then(|| (marker, len, trimmed[len..].trim()))
+}
+
+fn lint_fence_info(info: &str, messages: &mut Vec<String>) {
+    let attributes = info
+        .split([',', ' '])
+        .filter(|attribute| RUSTDOC_ATTRIBUTES.contains(attribute))
+        .collect::<Vec<_>>();
+
+    if !attributes.is_empty() {
+        messages.push(format!(
+            "Code block uses rustdoc attribute(s) `{}`, which Markdown renderers do not understand",
+            attributes.join(", ")
+        ));
+    } else if info.contains(',') {
+        messages.push(format!(
+            "Code block language `{info}` contains a comma, so will not be highlighted"
+        ));
+    }
+}
+
+/// Checks that headings will not be rendered deeper than Markdown allows
+/// at the depth the item is rendered at.
+fn lint_headings(item: &Item, messages: &mut Vec<String>) {
+    for (_, level, heading) in find_description_headings(item.description) {
+        // descriptions are rendered one level below the item's own heading
+        let rendered_level = item.depth + 1 + level;
+        if rendered_level > MAX_HEADING_LEVEL {
+            messages.push(format!(
+                "Heading `{heading}` would be rendered at level {rendered_level}, \
+                deeper than the maximum of {MAX_HEADING_LEVEL}, so will be bold text instead",
+            ));
+        }
+    }
+}
+
+/// Checks that any intra-doc links can be resolved.
+fn lint_links(item: &Item, resolver: &Resolver, messages: &mut Vec<String>) {
+    for link in find_intra_doc_links(item.description) {
+        if resolver
+            .resolve_link(&link.target, Some(item.element))
+            .is_none()
+        {
+            messages.push(format!(
+                "Link to `{}` does not refer to a type bindocs can resolve, \
+                so will not be a link in the output",
+                link.target
+            ));
+        }
+    }
+}
+
+fn lint_rust_syntax(line: &str, messages: &mut Vec<String>) {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    for syntax in RUST_SYNTAX {
+        let found = line.match_indices(syntax).any(|(start, _)| {
+            let before = line[..start].chars().next_back();
+            let after = line[start + syntax.len()..].chars().next();
+
+            // words must end at the end of the pattern too, ie `Self` but not `Selfie`
+            let ends = !syntax.ends_with(is_word_char) || !after.is_some_and(is_word_char);
+            !before.is_some_and(is_word_char) && ends
+        });
+
+        if found {
+            messages.push(format!(
+                "Mentions Rust-only syntax `{}`, which users may not understand",
+                syntax.trim()
+            ));
+        }
+    }
+}
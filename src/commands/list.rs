@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::io::{stdout, Write};
+
+use color_eyre::Result;
+
+use crate::module_path::ModulePath;
+use crate::resolver::Resolver;
+
+/// Prints the absolute path of every element,
+/// and whether its shorthand name can be used to inject it.
+pub fn run(resolver: &Resolver, module: Option<&str>) -> Result<()> {
+    let module = module.map(ModulePath::from);
+
+    let mut modules = resolver
+        .modules()
+        .iter()
+        .filter(|(path, _)| {
+            module
+                .as_ref()
+                .is_none_or(|module| path.starts_with(module))
+        })
+        .collect::<Vec<_>>();
+    modules.sort_by_key(|(path, _)| *path);
+
+    // shorthands are only unique if no other element in the crate shares the name
+    let mut counts = HashMap::<&str, usize>::new();
+    for info in resolver.modules().values().flat_map(|file| &file.elements) {
+        *counts.entry(&info.ident).or_default() += 1;
+    }
+
+    let rows = modules
+        .into_iter()
+        .flat_map(|(path, file)| file.elements.iter().map(move |info| (path, info)))
+        .map(|(path, info)| {
+            let absolute = path.join(&info.ident).to_string();
+            let shorthand = if counts[info.ident.as_str()] == 1 {
+                "unique"
+            } else {
+                "ambiguous"
+            };
+
+            (absolute, shorthand)
+        })
+        .collect::<Vec<_>>();
+
+    let width = rows.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
+
+    let mut stdout = stdout().lock();
+    for (path, shorthand) in rows {
+        writeln!(stdout, "{path:width$}  {shorthand}")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+mod coverage;
+mod dump;
+mod items;
+mod lint;
+mod list;
+mod show;
+
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use color_eyre::Result;
+
+use crate::RenderContext;
+pub use dump::DumpFormat;
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Output the types read from the crate in a machine-readable format,
+    /// for use by other tools.
+    Dump {
+        /// The format to output.
+        #[arg(short, long, default_value = "json")]
+        format: DumpFormat,
+
+        /// Path to write the output to.
+        /// Defaults to stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// List every struct, enum, type alias, constant and static which can be injected,
+    /// with its absolute path and whether its shorthand name is unique.
+    List {
+        /// Only list elements in this module and its submodules,
+        /// for example `config`.
+        #[arg(short, long)]
+        module: Option<String>,
+    },
+
+    /// Report the fields, variants and types which are missing doc comments.
+    Coverage {
+        /// Check every type in the crate,
+        /// rather than only those injected by the docs templates.
+        #[arg(long)]
+        all: bool,
+
+        /// Minimum percentage of items which must be documented.
+        /// If coverage is below this, the command fails.
+        #[arg(long)]
+        threshold: Option<f64>,
+    },
+
+    /// Check doc comments for text which will not work in user-facing docs,
+    /// such as links to code and Rust-only syntax.
+    Lint {
+        /// Check every type in the crate,
+        /// rather than only those injected by the docs templates.
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Render a single element, field or variant to stdout.
+    Show {
+        /// Path to the element, as used in templates.
+        /// For example `config::Config` or `Config.log_level`.
+        path: String,
+
+        /// Injection options, using the same syntax as in templates.
+        /// For example `'{ depth = 0 layout = "table" }'`.
+        #[arg(default_value = "")]
+        options: String,
+    },
+}
+
+/// Runs the subcommand against the resolved crate.
+pub fn run(
+    command: Command,
+    context: &RenderContext,
+    project_path: &Path,
+    docs_path: &Path,
+) -> Result<()> {
+    let resolver = context.replace.resolver;
+
+    match command {
+        Command::Dump { format, output } => {
+            dump::run(resolver, project_path, format, output.as_deref())
+        }
+        Command::List { module } => list::run(resolver, module.as_deref()),
+        Command::Coverage { all, threshold } => {
+            coverage::run(context, project_path, docs_path, all, threshold)
+        }
+        Command::Lint { all } => lint::run(context, project_path, docs_path, all),
+        Command::Show { path, options } => show::run(context, &path, &options),
+    }
+}
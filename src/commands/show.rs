@@ -0,0 +1,56 @@
+use std::io::{stdout, Write};
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+
+use crate::renderer::{MarkdownRenderer, OutputFormat, Renderer};
+use crate::replacer::{parse_options, Replacer};
+use crate::RenderContext;
+
+/// Renders a single element, field or variant to stdout.
+pub fn run(context: &RenderContext, path: &str, options: &str) -> Result<()> {
+    let resolver = context.replace.resolver;
+
+    let Some(resolved) = resolver.resolve_path(path) else {
+        let name = path.rsplit("::").next().unwrap_or(path);
+
+        let mut candidates = resolver
+            .modules()
+            .iter()
+            .flat_map(|(module, file)| file.elements.iter().map(move |info| (module, info)))
+            .filter(|(_, info)| info.ident == name || info.name == name)
+            .map(|(module, info)| module.join(&info.ident).to_string())
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        return if candidates.is_empty() {
+            Err(eyre!("Could not resolve '{path}'"))
+        } else {
+            Err(eyre!(
+                "Could not resolve '{path}', did you mean one of: {}",
+                candidates.join(", ")
+            ))
+        };
+    };
+
+    let options = if options.trim().is_empty() {
+        context.replace.defaults.clone()
+    } else {
+        parse_options(options, context.replace.defaults)?
+    };
+
+    let output = match context.format {
+        OutputFormat::Markdown => {
+            let renderer =
+                MarkdownRenderer::new(String::new(), context.options, context.replace.resolver);
+            let mut replacer = Replacer::new(renderer, &context.replace);
+
+            replacer.render(resolved, options)?;
+            replacer.finish()
+        }
+    };
+
+    write!(stdout(), "{}", output.trim_start())?;
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::renderer::{OutputFormat, SlugStyle};
+use crate::replacer::ReplaceOptions;
+
+/// Name of the standalone config file, relative to the crate root.
+pub const CONFIG_FILE_NAME: &str = "bindocs.corn";
+
+/// Project-wide configuration.
+///
+/// This is loaded from `bindocs.corn` in the crate root if it exists,
+/// otherwise from `[package.metadata.bindocs]` in `Cargo.toml`.
+/// Any matching CLI flags take precedence.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Path to the document template(s), relative to the crate root.
+    /// Defaults to `docs`.
+    pub docs_path: Option<PathBuf>,
+
+    /// Path to output the rendered doc(s), relative to the crate root.
+    /// Defaults to `target/bindoc`.
+    pub output_path: Option<PathBuf>,
+
+    /// Glob patterns of files in the docs directory to render as templates,
+    /// relative to the docs directory.
+    /// Any other files are treated as assets.
+    /// Defaults to `[ "*.md" ]`.
+    pub templates: Vec<String>,
+
+    /// How to handle non-template files in the docs directory.
+    /// Defaults to `copy`.
+    pub assets: AssetMode,
+
+    /// Path to the directory containing custom element templates,
+    /// relative to the docs directory.
+    /// Defaults to `_templates`.
+    pub element_templates: PathBuf,
+
+    /// Whether to delete files written to the output directory by the previous run
+    /// which no longer have a matching file in the docs directory.
+    /// Defaults to false.
+    pub clean: bool,
+
+    /// The format to render documentation as.
+    /// Defaults to `markdown`.
+    pub format: OutputFormat,
+
+    /// The algorithm used to generate heading anchors for tables of contents.
+    /// This should match the tool the output is viewed with.
+    /// Defaults to `github`.
+    pub slugs: SlugStyle,
+
+    /// Default options used for every injection.
+    /// Options set on an individual injection are applied over the top of these.
+    pub defaults: ReplaceOptions,
+
+    /// Whether to simplify types for end-users,
+    /// for example by showing `Option<String>` as `String?`.
+    /// Defaults to true.
+    pub simplified_types: bool,
+
+    /// Map of Rust type names to the names that should be shown in their place.
+    /// For example `{ PathBuf = "path" }`.
+    pub type_names: HashMap<String, String>,
+
+    /// Crate features to treat as enabled when evaluating `#[cfg(feature = "...")]` attributes.
+    pub features: Vec<String>,
+
+    /// Glob patterns of source files to read types from, relative to the crate `src` folder.
+    /// If empty, all files are included.
+    pub include: Vec<String>,
+
+    /// Glob patterns of source files to ignore, relative to the crate `src` folder.
+    pub exclude: Vec<String>,
+
+    /// Extra variables which can be output in templates using `<% @var name %>`.
+    /// These take precedence over variables read from `Cargo.toml`.
+    pub variables: HashMap<String, String>,
+
+    /// Whether to fail when a template references an unknown variable,
+    /// instead of logging a warning.
+    /// Defaults to false.
+    pub strict: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            docs_path: None,
+            output_path: None,
+            templates: vec!["*.md".to_string()],
+            assets: AssetMode::default(),
+            element_templates: PathBuf::from("_templates"),
+            clean: false,
+            format: OutputFormat::default(),
+            slugs: SlugStyle::default(),
+            defaults: ReplaceOptions::default(),
+            simplified_types: true,
+            type_names: HashMap::new(),
+            features: vec![],
+            include: vec![],
+            exclude: vec![],
+            variables: HashMap::new(),
+            strict: false,
+        }
+    }
+}
+
+/// How non-template files are written to the output directory.
+#[derive(Deserialize, clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetMode {
+    /// Copy the file byte-for-byte.
+    #[default]
+    Copy,
+    /// Create a symlink to the original file.
+    Symlink,
+    /// Do not write the file to the output.
+    Skip,
+}
+
+impl Config {
+    /// Loads the config for the project at the given path.
+    ///
+    /// If `config_path` is provided, that file is used.
+    /// Otherwise `bindocs.corn` is tried, followed by the `Cargo.toml` metadata table.
+    /// If neither exist, the default config is returned.
+    pub fn load(project_path: &Path, config_path: Option<&Path>) -> Result<Self> {
+        if let Some(config_path) = config_path {
+            return Self::load_corn(config_path);
+        }
+
+        let corn_path = project_path.join(CONFIG_FILE_NAME);
+        if corn_path.exists() {
+            return Self::load_corn(&corn_path);
+        }
+
+        let manifest_path = project_path.join("Cargo.toml");
+        if manifest_path.exists() {
+            if let Some(config) = Self::load_manifest(&manifest_path)? {
+                return Ok(config);
+            }
+        }
+
+        debug!("No config found, using defaults");
+        Ok(Self::default())
+    }
+
+    fn load_corn(path: &Path) -> Result<Self> {
+        debug!("Loading config from {}", path.display());
+
+        let str = fs::read_to_string(path)?;
+        libcorn::from_str(&str).map_err(|err| eyre!("Invalid config '{}':\n{err}", path.display()))
+    }
+
+    fn load_manifest(path: &Path) -> Result<Option<Self>> {
+        let str = fs::read_to_string(path)?;
+        let manifest = str.parse::<toml::Table>()?;
+
+        let metadata = manifest
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("bindocs"));
+
+        match metadata {
+            Some(metadata) => {
+                debug!("Loading config from {}", path.display());
+                Ok(Some(metadata.clone().try_into()?))
+            }
+            None => Ok(None),
+        }
+    }
+}
@@ -1,37 +1,57 @@
+mod commands;
+mod config;
 mod module_path;
 mod parser;
 mod renderer;
 mod replacer;
 mod resolver;
 mod utils;
+mod variables;
 
 use clap::Parser;
 use std::fmt::{Display, Formatter};
 use std::fs;
 
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::process::exit;
 use std::time::Instant;
 
-use crate::renderer::{MarkdownRenderer, RenderOptions, Renderer};
-use crate::replacer::Replacer;
-use crate::resolver::Resolver;
+use crate::commands::Command;
+use crate::config::{AssetMode, Config};
+use crate::module_path::ModulePath;
+use crate::renderer::{ElementTemplates, MarkdownRenderer, OutputFormat, RenderOptions, Renderer};
+use crate::replacer::{ReplaceContext, Replacer};
+use crate::resolver::{ResolveOptions, Resolver};
+use crate::utils::build_glob_set;
+use crate::variables::Variables;
 use color_eyre::Result;
+use globset::GlobSet;
 use pathdiff::diff_paths;
-use tracing::{error, info};
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::{debug, error, info};
 use walkdir::WalkDir;
 
+/// File in the output directory listing the files written by the last run,
+/// so that only those are deleted when cleaning.
+const MANIFEST_FILE: &str = ".bindocs-manifest";
+
 #[derive(Parser, Debug)]
 struct Args {
+    /// Command to run instead of rendering docs.
+    /// @bindocs skip
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the crate root.
     /// Defaults to current dir.
-    #[arg(short, long, default_value = ".")]
+    #[arg(short, long, default_value = ".", global = true)]
     project_path: PathBuf,
 
     /// Path to the document templates(s).
     /// This can be a file name for a single file, or a directory for multiple.
     /// Defaults to `<project_path>/docs`.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     docs_path: Option<PathBuf>,
 
     /// Path to output the rendered doc(s).
@@ -39,56 +59,247 @@ struct Args {
     /// Defaults to `<project_path>/target/bindoc`.
     #[arg(short, long)]
     output_path: Option<PathBuf>,
+
+    /// Path to the config file.
+    /// Defaults to `<project_path>/bindocs.corn`,
+    /// falling back to `[package.metadata.bindocs]` in `Cargo.toml`.
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// The format to render documentation as.
+    /// Defaults to `markdown`.
+    #[arg(short, long)]
+    format: Option<OutputFormat>,
+
+    /// Comma-separated list of crate features to treat as enabled.
+    #[arg(long, value_delimiter = ',', global = true)]
+    features: Vec<String>,
+
+    /// How to handle non-template files in the docs directory.
+    /// Defaults to `copy`.
+    #[arg(short, long)]
+    assets: Option<AssetMode>,
+
+    /// Delete files written to the output directory by the previous run
+    /// which no longer have a matching file in the docs directory.
+    #[arg(long)]
+    clean: bool,
+
+    /// Fail when a template references an unknown variable.
+    #[arg(long)]
+    strict: bool,
 }
 
-#[derive(Debug, Default)]
+#[derive(Serialize, Debug, Default)]
 pub struct TypeInfo {
     name: String,
     generics: Vec<TypeInfo>,
+    /// Module the type is used in, which its path is relative to.
+    /// `None` for text set by a `type` option, which does not refer to a real type.
+    #[serde(skip)]
+    module: Option<ModulePath>,
 }
 
-#[derive(Debug)]
+/// Location of an item in its source file.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Line number, starting at `1`.
+    line: usize,
+    /// Column number, in characters, starting at `1`.
+    column: usize,
+}
+
+#[derive(Serialize, Debug)]
 pub struct FieldInfo {
+    /// Field name in the source code,
+    /// or its position for tuple fields.
+    ident: String,
+    /// Field name after applying any rename rules.
     name: String,
     description: String,
+    #[serde(rename = "type")]
     ty: TypeInfo,
+    visibility: Visibility,
+    /// Version the field was added in.
+    since: Option<String>,
+    /// Text describing the default value, if known.
+    default: Option<String>,
+    /// Whether a value must be provided for the field,
+    /// ie it is not optional and has no default.
+    required: bool,
+    /// Name of the group to display the field under.
+    group: Option<String>,
+    /// Whether the field has a `#[serde(default)]` attribute,
+    /// either on itself or its container.
+    serde_default: bool,
+    location: SourceLocation,
 }
 
-#[derive(Debug)]
+impl FieldInfo {
+    /// Whether this is a tuple field, named by its position.
+    pub fn is_positional(&self) -> bool {
+        self.ident.chars().all(|c| c.is_ascii_digit())
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// `pub`
+    Public,
+    /// `pub(crate)`
+    Crate,
+    /// `pub(super)` or `pub(in path)`
+    Restricted,
+    /// No visibility modifier, or `pub(self)`
+    Private,
+}
+
+#[derive(Serialize, Debug)]
 pub struct StructInfo {
     fields: Vec<FieldInfo>,
 }
 
-#[derive(Debug)]
+impl StructInfo {
+    /// Gets the field of a newtype struct, such as `struct Port(u16)`.
+    pub fn newtype_field(&self) -> Option<&FieldInfo> {
+        match &self.fields[..] {
+            [field] if field.is_positional() => Some(field),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
 pub struct VariantInfo {
+    /// Variant name in the source code.
+    ident: String,
+    /// Variant name after applying any rename rules.
     name: String,
     description: String,
     fields: Vec<FieldInfo>,
+    /// Explicit discriminant value, such as the `10` in `Debug = 10`,
+    /// if it is a literal.
+    discriminant: Option<String>,
+    /// Version the variant was added in.
+    since: Option<String>,
+    /// Name of the group to display the variant under.
+    group: Option<String>,
+    location: SourceLocation,
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Debug)]
 pub struct EnumInfo {
     variants: Vec<VariantInfo>,
 }
 
-#[derive(Debug)]
+impl EnumInfo {
+    /// Whether every variant is a unit variant,
+    /// in which case the enum is a choice between a set of values.
+    pub fn is_unit_only(&self) -> bool {
+        !self.variants.is_empty() && self.variants.iter().all(|v| v.fields.is_empty())
+    }
+}
+
+/// A type alias, such as `type Widgets = Vec<WidgetConfig>`.
+#[derive(Serialize, Debug)]
+pub struct AliasInfo {
+    /// The aliased type.
+    #[serde(rename = "type")]
+    ty: TypeInfo,
+}
+
+/// A `const` or `static` item.
+#[derive(Serialize, Debug)]
+pub struct ConstInfo {
+    #[serde(rename = "type")]
+    ty: TypeInfo,
+    /// The value, if it is a literal which can be read without compiling the crate.
+    value: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
 pub enum ElementInfo {
     Struct(StructInfo),
     Enum(EnumInfo),
+    Alias(AliasInfo),
+    Const(ConstInfo),
+    Static(ConstInfo),
 }
 
-#[derive(Debug)]
+impl ElementInfo {
+    /// Gets the name of the kind of element,
+    /// as used for template names and in the dump output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ElementInfo::Struct(_) => "struct",
+            ElementInfo::Enum(_) => "enum",
+            ElementInfo::Alias(_) => "alias",
+            ElementInfo::Const(_) => "const",
+            ElementInfo::Static(_) => "static",
+        }
+    }
+
+    /// Gets the type a value of the element is documented as,
+    /// for newtype structs, type aliases, constants and statics.
+    pub fn value_type(&self) -> Option<&TypeInfo> {
+        match self {
+            ElementInfo::Struct(info) => info.newtype_field().map(|field| &field.ty),
+            ElementInfo::Enum(_) => None,
+            ElementInfo::Alias(info) => Some(&info.ty),
+            ElementInfo::Const(info) | ElementInfo::Static(info) => Some(&info.ty),
+        }
+    }
+
+    /// Gets the value of a constant or static, if known.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            ElementInfo::Const(info) | ElementInfo::Static(info) => info.value.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
 pub struct Info {
+    /// Element name in the source code.
+    ident: String,
+    /// Module the element is declared in,
+    /// which links in its doc comments are relative to.
+    #[serde(skip)]
+    module: ModulePath,
+    /// Element name after applying any `name` override.
     name: String,
     description: String,
+    #[serde(flatten)]
     element: ElementInfo,
+    /// Version the element was added in.
+    since: Option<String>,
+    /// Names of the traits derived by the element.
+    derives: Vec<String>,
+    /// Names of the traits implemented for the element,
+    /// not including derives.
+    implements: Vec<String>,
+    location: SourceLocation,
+}
+
+/// A name brought into scope by a `use` item.
+#[derive(Debug)]
+pub struct Import {
+    /// Name the item is imported as, or `None` for glob imports.
+    name: Option<String>,
+    /// Path to the imported item, or to the module for glob imports,
+    /// relative to the importing module.
+    path: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct FileInfo {
     _name: String,
-    _path: PathBuf,
+    path: PathBuf,
     elements: Vec<Info>,
+    imports: Vec<Import>,
 }
 
 impl Display for Info {
@@ -98,7 +309,10 @@ impl Display for Info {
 }
 
 fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+    // logs are written to stderr so they do not mix with command output
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
 
     let start_time = Instant::now();
 
@@ -114,45 +328,101 @@ fn main() -> Result<()> {
         exit(2);
     };
 
+    let config = Config::load(&args.project_path, args.config.as_deref())?;
+
+    let features = if args.features.is_empty() {
+        config.features
+    } else {
+        args.features
+    };
+
+    let mut resolver = Resolver::new(
+        entry,
+        ResolveOptions {
+            features,
+            include: config.include,
+            exclude: config.exclude,
+        },
+    )?;
+    resolver.resolve()?;
+
     let docs_path = args
         .docs_path
         .take()
+        .or_else(|| config.docs_path.map(|path| args.project_path.join(path)))
         .unwrap_or_else(|| args.project_path.join("docs"));
 
-    if !docs_path.exists() {
-        eprintln!("Documentation path does not exist");
-        exit(1);
-    }
-
     let output_path = args
         .output_path
         .take()
+        .or_else(|| config.output_path.map(|path| args.project_path.join(path)))
         .unwrap_or_else(|| args.project_path.join("target/bindoc"));
 
-    let mut resolver = Resolver::new(entry);
-    resolver.resolve()?;
+    let format = args.format.unwrap_or(config.format);
 
     let options = RenderOptions {
-        simplified_types: true,
+        simplified_types: config.simplified_types,
+        type_names: config.type_names,
+        slug_style: config.slugs,
+    };
+
+    let variables = Variables::load(&args.project_path, &config.variables)?;
+
+    let templates_dir = if docs_path.is_file() {
+        docs_path.parent().expect("parent path to exist")
+    } else {
+        &docs_path
+    };
+    let element_templates_path = templates_dir.join(&config.element_templates);
+    let element_templates = ElementTemplates::new(&element_templates_path, format);
+
+    let context = RenderContext {
+        replace: ReplaceContext {
+            resolver: &resolver,
+            defaults: &config.defaults,
+            variables: &variables,
+            templates: &element_templates,
+            strict: args.strict || config.strict,
+        },
+        options: &options,
+        format,
+        templates: build_glob_set(&config.templates)?,
+        // templates mixed in with the docs cannot be told apart from them
+        element_templates: diff_paths(&element_templates_path, templates_dir)
+            .filter(|path| !path.as_os_str().is_empty()),
+        assets: args.assets.unwrap_or(config.assets),
     };
 
+    if let Some(command) = args.command.take() {
+        return commands::run(command, &context, &args.project_path, &docs_path);
+    }
+
+    if !docs_path.exists() {
+        eprintln!("Documentation path does not exist");
+        exit(1);
+    }
+
     if docs_path.is_file() {
-        process_file(
+        let file_output_path = get_output_path(
             &docs_path,
             docs_path.parent().expect("parent path to exist"),
             &output_path,
-            &resolver,
-            &options,
-        )?
+        );
+
+        process_template(&docs_path, &file_output_path, &context)?;
     } else {
         if !output_path.exists() {
             fs::create_dir_all(&output_path)?;
         }
 
+        let mut written = HashSet::new();
+
         for entry in WalkDir::new(&docs_path) {
             match entry {
                 Ok(entry) if entry.file_type().is_file() => {
-                    process_file(entry.path(), &docs_path, &output_path, &resolver, &options)?
+                    let file_output_path =
+                        process_file(entry.path(), &docs_path, &output_path, &context)?;
+                    written.extend(file_output_path);
                 }
                 Ok(_) => {}
                 Err(err) => {
@@ -160,6 +430,10 @@ fn main() -> Result<()> {
                 }
             }
         }
+
+        if args.clean || config.clean {
+            clean_output(&output_path, &written)?;
+        }
     }
 
     let elapsed = start_time.elapsed().as_secs_f64();
@@ -168,49 +442,190 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Shared state required to render each file.
+struct RenderContext<'a> {
+    replace: ReplaceContext<'a>,
+    options: &'a RenderOptions,
+    format: OutputFormat,
+    /// Files to render as templates.
+    templates: GlobSet,
+    /// Directory containing the element templates, relative to the docs directory.
+    element_templates: Option<PathBuf>,
+    assets: AssetMode,
+}
+
+impl RenderContext<'_> {
+    /// Checks whether the file is only used to render other files,
+    /// so should not be written to the output.
+    fn is_internal(&self, relative_path: &Path) -> bool {
+        is_private(relative_path)
+            || self
+                .element_templates
+                .as_ref()
+                .is_some_and(|path| relative_path.starts_with(path))
+    }
+}
+
+/// Renders the file if it is a template,
+/// otherwise writes it to the output according to the asset mode.
+///
+/// Returns the path written to, if any.
 fn process_file(
     file_path: &Path,
     docs_path: &Path,
     output_path: &Path,
-    resolver: &Resolver,
-    options: &RenderOptions,
+    context: &RenderContext,
+) -> Result<Option<PathBuf>> {
+    let file_output_path = get_output_path(file_path, docs_path, output_path);
+    let relative_path = diff_paths(file_path, docs_path).expect("relative path to exist");
+
+    if context.is_internal(&relative_path) {
+        debug!("Skipping private file: {}", relative_path.display());
+        return Ok(None);
+    }
+
+    if context.templates.is_match(&relative_path) {
+        process_template(file_path, &file_output_path, context)?;
+        return Ok(Some(file_output_path));
+    }
+
+    match context.assets {
+        AssetMode::Copy => {
+            debug!("Copying file: {}", file_output_path.display());
+
+            create_parent_dir(&file_output_path)?;
+
+            // avoid writing through a link left by a previous run
+            if file_output_path.is_symlink() {
+                fs::remove_file(&file_output_path)?;
+            }
+
+            fs::copy(file_path, &file_output_path)?;
+        }
+        AssetMode::Symlink => {
+            debug!("Linking file: {}", file_output_path.display());
+
+            create_parent_dir(&file_output_path)?;
+            if file_output_path.is_symlink() || file_output_path.exists() {
+                fs::remove_file(&file_output_path)?;
+            }
+
+            symlink_file(&file_path.canonicalize()?, &file_output_path)?;
+        }
+        AssetMode::Skip => return Ok(None),
+    }
+
+    Ok(Some(file_output_path))
+}
+
+fn process_template(
+    file_path: &Path,
+    file_output_path: &Path,
+    context: &RenderContext,
 ) -> Result<()> {
-    let file_output_path = if output_path_is_file_like(output_path) {
+    info!("Rendering file: {}", file_output_path.display());
+
+    let output = render_file(file_path, context)?;
+    write_file(file_output_path, output)?;
+
+    Ok(())
+}
+
+/// Checks whether the path is inside a directory, or is a file, starting with `_`.
+/// These are only used by other templates, for example as includes,
+/// so are not written to the output.
+fn is_private(relative_path: &Path) -> bool {
+    relative_path
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with('_'))
+}
+
+fn get_output_path(file_path: &Path, docs_path: &Path, output_path: &Path) -> PathBuf {
+    if output_path_is_file_like(output_path) {
         output_path.to_path_buf()
     } else {
         let relative_path = diff_paths(file_path, docs_path).expect("relative path to exist");
         output_path.join(relative_path)
-    };
+    }
+}
 
-    info!("Rendering file: {}", file_output_path.display());
+/// Deletes any files written by the previous run which were not written during this run,
+/// then records the files written during this run in the manifest.
+///
+/// Only files listed in the manifest are deleted,
+/// so other files in the output directory are left untouched.
+fn clean_output(output_path: &Path, written: &HashSet<PathBuf>) -> Result<()> {
+    let manifest_path = output_path.join(MANIFEST_FILE);
+
+    if manifest_path.exists() {
+        for line in fs::read_to_string(&manifest_path)?.lines() {
+            // never follow paths out of the output directory
+            let relative = Path::new(line);
+            if !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                continue;
+            }
 
-    let output = render_file(file_path, resolver, options)?;
-    write_file(&file_output_path, output)?;
+            let path = output_path.join(relative);
+            if !written.contains(&path) && (path.is_file() || path.is_symlink()) {
+                info!("Removing stale file: {}", path.display());
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+
+    let mut manifest = written
+        .iter()
+        .filter_map(|path| path.strip_prefix(output_path).ok())
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    manifest.sort();
+
+    fs::write(manifest_path, manifest.join("\n"))?;
 
     Ok(())
 }
 
-fn render_file(path: &Path, resolver: &Resolver, options: &RenderOptions) -> Result<String> {
-    let output = String::new();
-    let renderer = MarkdownRenderer::new(output, options);
+fn render_file(path: &Path, context: &RenderContext) -> Result<String> {
+    match context.format {
+        OutputFormat::Markdown => {
+            let renderer =
+                MarkdownRenderer::new(String::new(), context.options, context.replace.resolver);
+            let mut replacer = Replacer::new(renderer, &context.replace);
 
-    let input = fs::read_to_string(path)?;
-    let mut replacer = Replacer::new(renderer, resolver);
-
-    replacer.replace(input);
-    Ok(replacer.finish())
+            replacer.replace_file(path)?;
+            Ok(replacer.finish())
+        }
+    }
 }
 
 fn write_file(path: &Path, contents: String) -> Result<()> {
+    create_parent_dir(path)?;
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+fn create_parent_dir(path: &Path) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
 
-    fs::write(path, contents)?;
-
     Ok(())
 }
 
+#[cfg(unix)]
+fn symlink_file(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn symlink_file(original: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link)
+}
+
 fn output_path_is_file_like(output_path: &Path) -> bool {
     output_path.extension().is_some()
 }
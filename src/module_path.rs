@@ -3,7 +3,7 @@ use std::path::{Path, PathBuf};
 
 use crate::utils::PathExt;
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct ModulePath {
     segments: Vec<String>,
 }
@@ -33,6 +33,11 @@ impl ModulePath {
         parent
     }
 
+    /// Checks whether this path is equal to or inside the given module.
+    pub fn starts_with(&self, other: &ModulePath) -> bool {
+        self.segments.starts_with(&other.segments)
+    }
+
     pub fn element(&self) -> Option<&str> {
         self.segments.last().map(|last| last.as_str())
     }
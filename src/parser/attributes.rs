@@ -0,0 +1,172 @@
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, ExprLit, Lit, LitStr, Meta, Token};
+use tracing::warn;
+
+use crate::parser::cfg::is_predicate_enabled;
+
+/// Prefix for doc comment lines containing bindocs options.
+pub const DOC_MARKER: &str = "@bindocs";
+
+/// Presentation options set next to an item in the code.
+///
+/// These can be set using a doc comment line starting with `@bindocs`,
+/// or a `#[bindocs(...)]` attribute if the crate provides one.
+#[derive(Debug, Default)]
+pub struct BindocsAttributes {
+    /// Leave the item out of the documentation.
+    /// Also set by `#[doc(hidden)]`.
+    pub skip: bool,
+    /// Name to display instead of the item name.
+    pub name: Option<String>,
+    /// Type text to display instead of the real type.
+    pub ty: Option<String>,
+    /// Version the item was added in.
+    pub since: Option<String>,
+    /// Default value text to display.
+    pub default: Option<String>,
+    /// Name of the group to display the item under.
+    pub group: Option<String>,
+}
+
+/// Checks whether the item should be documented,
+/// taking into account cfg attributes and any options hiding it.
+pub fn should_document(attrs: &[Attribute], features: &[String]) -> bool {
+    super::is_enabled(attrs, features) && !parse_attributes(attrs, features).skip
+}
+
+/// Reads the bindocs options from the item's attributes and doc comments.
+/// Options inside a `cfg_attr` are only read if its predicate is enabled.
+pub fn parse_attributes(attrs: &[Attribute], features: &[String]) -> BindocsAttributes {
+    let mut options = BindocsAttributes::default();
+
+    for attr in attrs {
+        if attr.path().is_ident("doc") {
+            match &attr.meta {
+                Meta::List(_) => {
+                    attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("hidden") {
+                            options.skip = true;
+                        }
+
+                        Ok(())
+                    })
+                    .ok();
+                }
+                Meta::NameValue(name_value) => {
+                    if let Expr::Lit(ExprLit {
+                        lit: Lit::Str(str), ..
+                    }) = &name_value.value
+                    {
+                        for line in str.value().lines() {
+                            if let Some(marker) = line.trim().strip_prefix(DOC_MARKER) {
+                                parse_marker(marker, &mut options);
+                            }
+                        }
+                    }
+                }
+                Meta::Path(_) => {}
+            }
+        } else if attr.path().is_ident("bindocs") {
+            attr.parse_nested_meta(|meta| parse_option(meta, &mut options))
+                .unwrap_or_else(|err| warn!("Invalid bindocs attribute: {err}"));
+        } else if attr.path().is_ident("cfg_attr") {
+            let nested = attr
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .unwrap_or_default();
+
+            let mut nested = nested.iter();
+            if !nested
+                .next()
+                .is_some_and(|predicate| is_predicate_enabled(predicate, features))
+            {
+                continue;
+            }
+
+            for meta in nested {
+                if let Meta::List(list) = meta {
+                    if list.path.is_ident("bindocs") {
+                        list.parse_nested_meta(|meta| parse_option(meta, &mut options))
+                            .unwrap_or_else(|err| warn!("Invalid bindocs attribute: {err}"));
+                    }
+                }
+            }
+        }
+    }
+
+    options
+}
+
+/// Gets the names of the traits derived by the item,
+/// including those derived inside an enabled `cfg_attr`.
+/// Only the last segment of each path is kept, ie `serde::Deserialize` becomes `Deserialize`.
+pub fn parse_derives(attrs: &[Attribute], features: &[String]) -> Vec<String> {
+    let mut derives = vec![];
+
+    let mut push_derives = |meta: &Meta| {
+        if let Meta::List(list) = meta {
+            if list.path.is_ident("derive") {
+                let paths = list
+                    .parse_args_with(Punctuated::<syn::Path, Token![,]>::parse_terminated)
+                    .unwrap_or_default();
+
+                derives.extend(
+                    paths
+                        .iter()
+                        .filter_map(|path| path.segments.last())
+                        .map(|segment| segment.ident.to_string()),
+                );
+            }
+        }
+    };
+
+    for attr in attrs {
+        if attr.path().is_ident("derive") {
+            push_derives(&attr.meta);
+        } else if attr.path().is_ident("cfg_attr") {
+            let nested = attr
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .unwrap_or_default();
+
+            let mut nested = nested.iter();
+            if nested
+                .next()
+                .is_some_and(|predicate| is_predicate_enabled(predicate, features))
+            {
+                nested.for_each(&mut push_derives);
+            }
+        }
+    }
+
+    derives
+}
+
+/// Parses the options from a doc comment marker,
+/// which uses the same syntax as the attribute,
+/// for example `@bindocs skip` or `@bindocs name = "foo", since = "1.2"`.
+fn parse_marker(marker: &str, options: &mut BindocsAttributes) {
+    let parser = syn::meta::parser(|meta| parse_option(meta, options));
+
+    syn::parse::Parser::parse_str(parser, marker.trim())
+        .unwrap_or_else(|err| warn!("Invalid {DOC_MARKER} doc comment: {err}"));
+}
+
+fn parse_option(meta: ParseNestedMeta, options: &mut BindocsAttributes) -> syn::Result<()> {
+    if meta.path.is_ident("skip") {
+        options.skip = true;
+    } else if meta.path.is_ident("name") {
+        options.name = Some(meta.value()?.parse::<LitStr>()?.value());
+    } else if meta.path.is_ident("type") {
+        options.ty = Some(meta.value()?.parse::<LitStr>()?.value());
+    } else if meta.path.is_ident("since") {
+        options.since = Some(meta.value()?.parse::<LitStr>()?.value());
+    } else if meta.path.is_ident("default") {
+        options.default = Some(meta.value()?.parse::<LitStr>()?.value());
+    } else if meta.path.is_ident("group") {
+        options.group = Some(meta.value()?.parse::<LitStr>()?.value());
+    } else {
+        return Err(meta.error("unknown bindocs option"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, ExprLit, Lit, Meta, Token};
+
+/// Checks whether the item's `#[cfg(...)]` attributes are satisfied
+/// by the enabled features.
+///
+/// Only `feature` and `test` predicates can be evaluated statically.
+/// Anything else (`unix`, `target_os`, etc.) is assumed to be enabled.
+pub fn is_enabled(attrs: &[Attribute], features: &[String]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .filter_map(|attr| attr.parse_args::<Meta>().ok())
+        .all(|meta| evaluate(&meta, features).unwrap_or(true))
+}
+
+/// Checks whether a single cfg predicate, such as in a `cfg_attr`,
+/// is satisfied by the enabled features.
+/// Predicates which cannot be evaluated are assumed to be enabled.
+pub fn is_predicate_enabled(meta: &Meta, features: &[String]) -> bool {
+    evaluate(meta, features).unwrap_or(true)
+}
+
+/// Evaluates a single cfg predicate.
+/// Returns `None` if the predicate cannot be evaluated.
+fn evaluate(meta: &Meta, features: &[String]) -> Option<bool> {
+    match meta {
+        Meta::Path(path) if path.is_ident("test") => Some(false),
+        Meta::Path(_) => None,
+        Meta::NameValue(name_value) if name_value.path.is_ident("feature") => {
+            match &name_value.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(str), ..
+                }) => Some(features.contains(&str.value())),
+                _ => None,
+            }
+        }
+        Meta::NameValue(_) => None,
+        Meta::List(list) => {
+            let nested = list
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .ok()?;
+
+            let results = nested
+                .iter()
+                .map(|meta| evaluate(meta, features))
+                .collect::<Vec<_>>();
+
+            if list.path.is_ident("all") {
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(true)
+                }
+            } else if list.path.is_ident("any") {
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(false)
+                }
+            } else if list.path.is_ident("not") {
+                results.first().copied().flatten().map(|res| !res)
+            } else {
+                None
+            }
+        }
+    }
+}
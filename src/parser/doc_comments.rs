@@ -1,40 +1,29 @@
+use std::fs;
+
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Attribute, Expr, ExprLit, ExprMacro, Lit, Meta, Token};
+use tracing::warn;
+
+use crate::parser::attributes::DOC_MARKER;
+use crate::parser::cfg::is_predicate_enabled;
+use crate::parser::{get_location, ParseContext};
+
 /// Taken from `clap_derive`:
 /// <https://github.com/clap-rs/clap/blob/74109e5c1aa44cc7cd42f850fd73d091235bed7c/clap_derive/src/utils/doc_comments.rs#L8-L50>
-pub fn extract_doc_comment(attrs: &[syn::Attribute]) -> String {
+///
+/// As well as string literals, doc attributes using `include_str!` and `concat!`,
+/// and doc attributes inside an enabled `cfg_attr`, are supported.
+pub fn extract_doc_comment(attrs: &[Attribute], context: &ParseContext) -> String {
     // multiline comments (`/** ... */`) may have LFs (`\n`) in them,
     // we need to split so we could handle the lines correctly
     //
     // we also need to remove leading and trailing blank lines
     let lines: Vec<_> = attrs
         .iter()
-        .filter(|attr| attr.path().is_ident("doc"))
-        .filter_map(|attr| {
-            // non #[doc = "..."] attributes are not our concern
-            // we leave them for rustc to handle
-            match &attr.meta {
-                syn::Meta::NameValue(syn::MetaNameValue {
-                    value:
-                        syn::Expr::Lit(syn::ExprLit {
-                            lit: syn::Lit::Str(s),
-                            ..
-                        }),
-                    ..
-                }) => Some(s.value()),
-                _ => None,
-            }
-        })
-        // .skip_while(|s| is_blank(s))
-        .flat_map(|s| {
-            let lines = s
-                .split('\n')
-                .map(|s| {
-                    // remove one leading space no matter what
-                    let s = s.strip_prefix(' ').unwrap_or(s);
-                    s.to_owned()
-                })
-                .collect::<Vec<_>>();
-            lines
-        })
+        .flat_map(|attr| doc_lines(&attr.meta, context))
+        // bindocs options are not part of the description
+        .filter(|s| !s.trim_start().starts_with(DOC_MARKER))
         .collect();
 
     // while let Some(true) = lines.last().map(|s| is_blank(s)) {
@@ -43,3 +32,125 @@ pub fn extract_doc_comment(attrs: &[syn::Attribute]) -> String {
 
     lines.join("\n")
 }
+
+/// Gets the lines of text from a doc attribute,
+/// or from each doc attribute inside a `cfg_attr` if its predicate is enabled.
+fn doc_lines(meta: &Meta, context: &ParseContext) -> Vec<String> {
+    match meta {
+        Meta::NameValue(name_value) if name_value.path.is_ident("doc") => {
+            match &name_value.value {
+                // doc comments (`/// ...`) are literals, usually with a leading space
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => s
+                    .value()
+                    .split('\n')
+                    .map(|s| {
+                        // remove one leading space no matter what
+                        let s = s.strip_prefix(' ').unwrap_or(s);
+                        s.to_owned()
+                    })
+                    .collect(),
+                expr => evaluate(expr, context)
+                    .map(|value| value.trim_end().lines().map(str::to_owned).collect())
+                    .unwrap_or_else(|| {
+                        let location = get_location(expr.span());
+                        warn!(
+                            "Could not evaluate doc attribute at {}:{}, \
+                            so it is not included in the description",
+                            context.file.display(),
+                            location.line
+                        );
+                        vec![]
+                    }),
+            }
+        }
+        Meta::List(list) if list.path.is_ident("cfg_attr") => {
+            let nested = list
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .unwrap_or_default();
+
+            let mut nested = nested.iter();
+            match nested.next() {
+                Some(predicate) if is_predicate_enabled(predicate, context.features) => {
+                    nested.flat_map(|meta| doc_lines(meta, context)).collect()
+                }
+                _ => vec![],
+            }
+        }
+        // non #[doc = "..."] attributes are not our concern
+        // we leave them for rustc to handle
+        _ => vec![],
+    }
+}
+
+/// Evaluates the value of a doc attribute,
+/// supporting literals and the `include_str!`, `concat!` and `env!("CARGO_MANIFEST_DIR")` macros.
+///
+/// Returns `None` if the value cannot be evaluated statically.
+fn evaluate(expr: &Expr, context: &ParseContext) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(s) => Some(s.value()),
+            Lit::Char(c) => Some(c.value().to_string()),
+            Lit::Int(int) => Some(int.base10_digits().to_string()),
+            Lit::Float(float) => Some(float.base10_digits().to_string()),
+            Lit::Bool(bool) => Some(bool.value.to_string()),
+            _ => None,
+        },
+        Expr::Group(group) => evaluate(&group.expr, context),
+        Expr::Macro(ExprMacro { mac, .. }) => {
+            let args = mac
+                .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                .ok()?;
+
+            if mac.path.is_ident("concat") {
+                args.iter().map(|arg| evaluate(arg, context)).collect()
+            } else if mac.path.is_ident("include_str") {
+                let [path] = &args.iter().collect::<Vec<_>>()[..] else {
+                    return None;
+                };
+
+                // paths are relative to the file containing the macro
+                let path = context
+                    .file
+                    .parent()
+                    .unwrap_or(context.manifest_dir)
+                    .join(evaluate(path, context)?);
+
+                fs::read_to_string(&path)
+                    .inspect_err(|err| warn!("Failed to read {}: {err}", path.display()))
+                    .ok()
+            } else if mac.path.is_ident("env") {
+                match &args.iter().collect::<Vec<_>>()[..] {
+                    [Expr::Lit(ExprLit {
+                        lit: Lit::Str(name),
+                        ..
+                    })] if name.value() == "CARGO_MANIFEST_DIR" => {
+                        Some(context.manifest_dir.display().to_string())
+                    }
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Finds the default value from a "Defaults to ..." sentence in a doc comment.
+pub fn extract_default(description: &str) -> Option<String> {
+    const PREFIX: &str = "Defaults to ";
+
+    description.lines().find_map(|line| {
+        let start = line.find(PREFIX)? + PREFIX.len();
+        let value = line[start..].trim().trim_end_matches('.');
+
+        if value.is_empty() {
+            None
+        } else {
+            Some(value.to_string())
+        }
+    })
+}
@@ -0,0 +1,117 @@
+use pulldown_cmark::{BrokenLink, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+/// An intra-doc link in a doc comment,
+/// such as ``[`Config`]`` or `[the config](crate::Config)`.
+#[derive(Debug)]
+pub struct IntraDocLink<'a> {
+    /// Byte range of the whole link within the text.
+    pub range: Range<usize>,
+    /// Text inside the square brackets.
+    pub text: &'a str,
+    /// Path the link points to, with disambiguators such as `struct@`
+    /// and trailing `()` or `!` removed.
+    pub target: String,
+}
+
+/// Finds the intra-doc links in a doc comment.
+///
+/// The text is parsed as CommonMark, so brackets inside code
+/// and links with a matching reference definition are not included.
+pub fn find_intra_doc_links(text: &str) -> Vec<IntraDocLink<'_>> {
+    let parser = Parser::new_with_broken_link_callback(text, Options::empty(), Some(broken_link));
+
+    let mut links = vec![];
+    // range, target and the range of the link text so far
+    let mut current: Option<(Range<usize>, String, Option<Range<usize>>)> = None;
+
+    for (event, range) in parser.into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link {
+                link_type:
+                    LinkType::Inline
+                    | LinkType::ReferenceUnknown
+                    | LinkType::CollapsedUnknown
+                    | LinkType::ShortcutUnknown,
+                dest_url,
+                ..
+            }) => {
+                current = intra_doc_target(&dest_url).map(|target| (range, target, None));
+            }
+            Event::End(TagEnd::Link) => {
+                if let Some((range, target, content)) = current.take() {
+                    links.push(IntraDocLink {
+                        range,
+                        text: content.map_or("", |content| &text[content]),
+                        target,
+                    });
+                }
+            }
+            _ => {
+                if let Some((_, _, content)) = &mut current {
+                    let start = content.as_ref().map_or(range.start, |c| c.start);
+                    *content = Some(start..range.end);
+                }
+            }
+        }
+    }
+
+    links
+}
+
+/// Replaces each intra-doc link in the text with the output of `replace`.
+/// Links are left unchanged where `replace` returns `None`.
+pub fn replace_intra_doc_links<F>(text: &str, mut replace: F) -> String
+where
+    F: FnMut(&IntraDocLink) -> Option<String>,
+{
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for link in find_intra_doc_links(text) {
+        if let Some(replacement) = replace(&link) {
+            output.push_str(&text[last..link.range.start]);
+            output.push_str(&replacement);
+            last = link.range.end;
+        }
+    }
+
+    output.push_str(&text[last..]);
+    output
+}
+
+/// Treats reference links without a definition, such as ``[`Config`]``,
+/// as links to their own text, so they can be checked by [`intra_doc_target`].
+fn broken_link(link: BrokenLink<'_>) -> Option<(CowStr<'_>, CowStr<'_>)> {
+    Some((link.reference, CowStr::Borrowed("")))
+}
+
+/// Gets the path a link destination points to, if it is an intra-doc link.
+///
+/// Only backticked names and `::` paths are treated as intra-doc links,
+/// as other text in brackets is more likely to be prose or a URL.
+fn intra_doc_target(destination: &str) -> Option<String> {
+    let code = destination
+        .strip_prefix('`')
+        .and_then(|destination| destination.strip_suffix('`'));
+    let target = code.unwrap_or(destination).trim();
+
+    let is_intra_doc = code.is_some() || target.contains("::");
+    (is_intra_doc && is_rust_path(target)).then(|| strip_disambiguators(target).to_string())
+}
+
+/// Checks whether the text looks like a Rust path, such as `config::Config`.
+fn is_rust_path(text: &str) -> bool {
+    let path = strip_disambiguators(text);
+
+    !path.is_empty()
+        && path.split("::").all(|segment| {
+            segment.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+        })
+}
+
+fn strip_disambiguators(text: &str) -> &str {
+    let text = text.split_once('@').map_or(text, |(_, path)| path);
+    text.trim_end_matches("()").trim_end_matches('!')
+}
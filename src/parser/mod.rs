@@ -1,54 +1,121 @@
+use proc_macro2::Span;
 use std::fmt::{Display, Formatter};
-use syn::{Field, Fields, GenericArgument, ItemEnum, ItemStruct, PathArguments, Type};
+use std::path::Path;
+use syn::spanned::Spanned;
+use syn::{
+    Expr, ExprLit, Field, Fields, GenericArgument, ItemEnum, ItemStruct, ItemType, ItemUse, Lit,
+    PathArguments, Type, UnOp, UseTree,
+};
 
-pub use crate::parser::doc_comments::extract_doc_comment;
-use crate::parser::rename_rule::{get_rename_rule, RenameRule};
-use crate::{EnumInfo, FieldInfo, StructInfo, TypeInfo, VariantInfo};
+use crate::module_path::ModulePath;
+pub use crate::parser::attributes::{parse_attributes, parse_derives, should_document};
+pub use crate::parser::cfg::is_enabled;
+pub use crate::parser::doc_comments::{extract_default, extract_doc_comment};
+pub use crate::parser::links::{find_intra_doc_links, replace_intra_doc_links};
+use crate::parser::rename_rule::{get_rename, get_rename_rule, has_serde_default, RenameRule};
+use crate::renderer::RenderOptions;
+use crate::{
+    AliasInfo, ConstInfo, EnumInfo, FieldInfo, Import, SourceLocation, StructInfo, TypeInfo,
+    VariantInfo, Visibility,
+};
 
+mod attributes;
+mod cfg;
 mod doc_comments;
+mod links;
 mod rename_rule;
 
-pub fn parse_struct(item: ItemStruct) -> StructInfo {
+/// Information about the source file items are parsed from.
+pub struct ParseContext<'a> {
+    /// Enabled crate features.
+    pub features: &'a [String],
+    /// Module the items are declared in.
+    pub module: &'a ModulePath,
+    /// Path to the source file.
+    pub file: &'a Path,
+    /// Path to the crate root, used as `CARGO_MANIFEST_DIR` in doc attributes.
+    pub manifest_dir: &'a Path,
+}
+
+pub fn parse_struct(item: ItemStruct, context: &ParseContext) -> StructInfo {
     let rename_rule = get_rename_rule(&item.attrs);
+    let container_default = has_serde_default(&item.attrs);
 
     let fields = item
         .fields
         .into_iter()
-        .map(|f| parse_field(f, rename_rule))
+        .enumerate()
+        .filter(|(_, f)| should_document(&f.attrs, context.features))
+        .map(|(index, f)| parse_field(f, index, rename_rule, context))
+        .map(|field| FieldInfo {
+            required: field.required && !container_default,
+            serde_default: field.serde_default || container_default,
+            ..field
+        })
         .collect();
 
     StructInfo { fields }
 }
 
-pub fn parse_enum(item: ItemEnum) -> EnumInfo {
+pub fn parse_enum(item: ItemEnum, context: &ParseContext) -> EnumInfo {
     let rename_rule = get_rename_rule(&item.attrs);
 
     let variants = item
         .variants
         .into_iter()
+        .filter(|variant| should_document(&variant.attrs, context.features))
         .map(|variant| {
-            let name = rename_rule.apply_to_variant(&variant.ident.to_string());
+            let attributes = parse_attributes(&variant.attrs, context.features);
 
-            let description = extract_doc_comment(&variant.attrs);
+            let ident = variant.ident.to_string();
+            let location = get_location(variant.ident.span());
+            let name = attributes
+                .name
+                .or_else(|| get_rename(&variant.attrs))
+                .unwrap_or_else(|| rename_rule.apply_to_variant(&ident));
+
+            let description = extract_doc_comment(&variant.attrs, context);
+            let discriminant = variant
+                .discriminant
+                .as_ref()
+                .and_then(|(_, expr)| evaluate_item(expr));
 
             let fields = match variant.fields {
                 Fields::Named(fields) => fields
                     .named
                     .into_iter()
-                    .map(|f| parse_field(f, rename_rule))
+                    .enumerate()
+                    .filter(|(_, f)| should_document(&f.attrs, context.features))
+                    .map(|(index, f)| parse_field(f, index, rename_rule, context))
                     .collect(),
                 Fields::Unnamed(fields) => fields
                     .unnamed
                     .into_iter()
-                    .map(|f| parse_field(f, rename_rule))
+                    .enumerate()
+                    .filter(|(_, f)| should_document(&f.attrs, context.features))
+                    .map(|(index, f)| parse_field(f, index, rename_rule, context))
                     .collect(),
                 Fields::Unit => vec![],
             };
 
+            // variant fields are always as visible as the enum itself
+            let fields = fields
+                .into_iter()
+                .map(|field| FieldInfo {
+                    visibility: Visibility::Public,
+                    ..field
+                })
+                .collect();
+
             VariantInfo {
+                ident,
                 name,
                 description,
                 fields,
+                discriminant,
+                since: attributes.since,
+                group: attributes.group,
+                location,
             }
         })
         .collect();
@@ -56,25 +123,205 @@ pub fn parse_enum(item: ItemEnum) -> EnumInfo {
     EnumInfo { variants }
 }
 
-fn parse_field(field: Field, rename_rule: RenameRule) -> FieldInfo {
-    let name = field
-        .ident
-        .map(|ident| ident.to_string())
-        .map(|name| rename_rule.apply_to_field(&name))
-        .unwrap_or_default();
+/// Type aliases are documented as the type they alias,
+/// unless overridden by a `type` option.
+pub fn parse_alias(item: ItemType, context: &ParseContext) -> AliasInfo {
+    let ty = match parse_attributes(&item.attrs, context.features).ty {
+        Some(name) => TypeInfo {
+            name,
+            ..TypeInfo::default()
+        },
+        None => parse_type(item.ty, context),
+    };
 
-    let description = extract_doc_comment(&field.attrs);
-    let type_info = parse_type(Box::new(field.ty));
+    AliasInfo { ty }
+}
+
+/// Parses a `const` or `static` item's type and value.
+pub fn parse_const(ty: Box<Type>, expr: &Expr, context: &ParseContext) -> ConstInfo {
+    ConstInfo {
+        ty: parse_type(ty, context),
+        value: evaluate_literal(expr),
+    }
+}
+
+/// Gets the names brought into scope by a `use` item.
+pub fn parse_imports(item: &ItemUse) -> Vec<Import> {
+    let mut imports = vec![];
+    collect_imports(&item.tree, vec![], &mut imports);
+    imports
+}
+
+fn collect_imports(tree: &UseTree, mut path: Vec<String>, imports: &mut Vec<Import>) {
+    match tree {
+        UseTree::Path(tree) => {
+            path.push(tree.ident.to_string());
+            collect_imports(&tree.tree, path, imports);
+        }
+        // `use module::{self}` imports the module itself
+        UseTree::Name(tree) if tree.ident == "self" => imports.push(Import {
+            name: path.last().cloned(),
+            path,
+        }),
+        UseTree::Name(tree) => {
+            let name = tree.ident.to_string();
+            path.push(name.clone());
+            imports.push(Import {
+                name: Some(name),
+                path,
+            });
+        }
+        // `as _` only imports trait methods, so does not add a name
+        UseTree::Rename(tree) if tree.rename == "_" => {}
+        UseTree::Rename(tree) => {
+            path.push(tree.ident.to_string());
+            imports.push(Import {
+                name: Some(tree.rename.to_string()),
+                path,
+            });
+        }
+        UseTree::Glob(_) => imports.push(Import { name: None, path }),
+        UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_imports(tree, path.clone(), imports);
+            }
+        }
+    }
+}
+
+/// Renders the value of a literal expression, such as `5`, `-1.5` or `&["a", "b"]`.
+/// Top-level strings are rendered without quotes.
+///
+/// Returns `None` for anything else, as it cannot be evaluated without compiling the crate.
+fn evaluate_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(str), ..
+        }) => Some(str.value()),
+        Expr::Reference(reference) => evaluate_literal(&reference.expr),
+        Expr::Paren(paren) => evaluate_literal(&paren.expr),
+        Expr::Group(group) => evaluate_literal(&group.expr),
+        expr => evaluate_item(expr),
+    }
+}
+
+/// Renders a literal value inside another, with strings quoted.
+fn evaluate_item(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(str) => Some(format!("{:?}", str.value())),
+            Lit::Char(char) => Some(format!("{:?}", char.value())),
+            Lit::Int(int) => Some(int.base10_digits().to_string()),
+            Lit::Float(float) => Some(float.base10_digits().to_string()),
+            Lit::Bool(bool) => Some(bool.value.to_string()),
+            _ => None,
+        },
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => {
+            evaluate_item(&unary.expr).map(|value| format!("-{value}"))
+        }
+        Expr::Array(array) => {
+            let items = array
+                .elems
+                .iter()
+                .map(evaluate_item)
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(format!("[{}]", items.join(", ")))
+        }
+        Expr::Reference(reference) => evaluate_item(&reference.expr),
+        Expr::Paren(paren) => evaluate_item(&paren.expr),
+        Expr::Group(group) => evaluate_item(&group.expr),
+        _ => None,
+    }
+}
+
+/// Parses a struct or variant field.
+/// Tuple fields are named by their index.
+fn parse_field(
+    field: Field,
+    index: usize,
+    rename_rule: RenameRule,
+    context: &ParseContext,
+) -> FieldInfo {
+    let attributes = parse_attributes(&field.attrs, context.features);
+
+    let location = match &field.ident {
+        Some(ident) => get_location(ident.span()),
+        None => get_location(field.ty.span()),
+    };
+
+    let (ident, name) = match field.ident {
+        Some(ident) => {
+            let ident = ident.to_string();
+            let name =
+                get_rename(&field.attrs).unwrap_or_else(|| rename_rule.apply_to_field(&ident));
+            (ident, name)
+        }
+        None => (index.to_string(), index.to_string()),
+    };
+    let name = attributes.name.unwrap_or(name);
+
+    let description = extract_doc_comment(&field.attrs, context);
+    let default = attributes.default.or_else(|| extract_default(&description));
+
+    let type_info = match attributes.ty {
+        Some(name) => TypeInfo {
+            name,
+            ..TypeInfo::default()
+        },
+        None => parse_type(Box::new(field.ty), context),
+    };
+
+    let is_option = type_info.name.rsplit("::").next() == Some("Option");
+    let serde_default = has_serde_default(&field.attrs);
+    let required = default.is_none() && !is_option && !serde_default;
+
+    let visibility = parse_visibility(&field.vis);
 
     FieldInfo {
+        ident,
         name,
         description,
         ty: type_info,
+        visibility,
+        since: attributes.since,
+        default,
+        required,
+        group: attributes.group,
+        serde_default,
+        location,
+    }
+}
+
+/// Gets the location of the start of the span in its source file.
+pub fn get_location(span: Span) -> SourceLocation {
+    let start = span.start();
+
+    SourceLocation {
+        line: start.line,
+        column: start.column + 1,
+    }
+}
+
+fn parse_visibility(vis: &syn::Visibility) -> Visibility {
+    match vis {
+        syn::Visibility::Public(_) => Visibility::Public,
+        syn::Visibility::Restricted(restricted) if restricted.path.is_ident("crate") => {
+            Visibility::Crate
+        }
+        syn::Visibility::Restricted(restricted) if restricted.path.is_ident("self") => {
+            Visibility::Private
+        }
+        syn::Visibility::Restricted(_) => Visibility::Restricted,
+        syn::Visibility::Inherited => Visibility::Private,
     }
 }
 
-fn parse_type(ty: Box<Type>) -> TypeInfo {
-    let mut info = TypeInfo::default();
+fn parse_type(ty: Box<Type>, context: &ParseContext) -> TypeInfo {
+    let mut info = TypeInfo {
+        module: Some(context.module.clone()),
+        ..TypeInfo::default()
+    };
 
     match *ty {
         Type::Path(path) => {
@@ -85,7 +332,7 @@ fn parse_type(ty: Box<Type>) -> TypeInfo {
                 if let PathArguments::AngleBracketed(args) = segment.arguments {
                     for arg in args.args {
                         if let GenericArgument::Type(ty) = arg {
-                            info.generics.push(parse_type(Box::new(ty)))
+                            info.generics.push(parse_type(Box::new(ty), context))
                         }
                     }
                 }
@@ -93,6 +340,10 @@ fn parse_type(ty: Box<Type>) -> TypeInfo {
 
             info.name = name.join("::");
         }
+        // references are documented as the type they refer to, ie `&str` as `str`
+        Type::Reference(reference) => return parse_type(reference.elem, context),
+        Type::Paren(paren) => return parse_type(paren.elem, context),
+        Type::Group(group) => return parse_type(group.elem, context),
         _ => {
             info.name = "Unknown".to_string();
         }
@@ -121,28 +372,67 @@ impl Display for TypeInfo {
 }
 
 impl TypeInfo {
-    pub(crate) fn to_doc_string(&self, simplify: bool) -> String {
-        if simplify {
+    /// Formats the type for display,
+    /// using `lookup` to replace the text for any type it returns a value for.
+    /// Names set in the `type_names` option take precedence.
+    pub(crate) fn to_doc_string(
+        &self,
+        options: &RenderOptions,
+        lookup: &dyn Fn(&TypeInfo) -> Option<String>,
+    ) -> String {
+        // match on either the full path or just the type name
+        let mapped_name = options.type_names.get(&self.name).or_else(|| {
+            self.name
+                .rsplit("::")
+                .next()
+                .and_then(|name| options.type_names.get(name))
+        });
+
+        if let Some(name) = mapped_name {
+            return name.clone();
+        }
+
+        if let Some(text) = lookup(self) {
+            return text;
+        }
+
+        if options.simplified_types {
             match self.name.as_str() {
-                "Box" | "Arc" | "Rc" | "Cell" | "RefCell" | "RwLock" | "Mutex" => self
-                    .generics
-                    .iter()
-                    .map(|generic| generic.to_doc_string(simplify))
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                "Option" => format!(
-                    "{}?",
-                    self.generics
-                        .iter()
-                        .map(|generic| generic.to_doc_string(simplify))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ),
+                "Box" | "Arc" | "Rc" | "Cell" | "RefCell" | "RwLock" | "Mutex" => {
+                    self.generics_doc_string(options, lookup)
+                }
+                "Option" => {
+                    let inner = self.generics_doc_string(options, lookup);
+                    // lists of values need grouping, ie `('a' | 'b')?`
+                    if inner.contains(" | ") {
+                        format!("({inner})?")
+                    } else {
+                        format!("{inner}?")
+                    }
+                }
                 "str" => "String".to_string(),
                 _ => self.name.clone(),
             }
+        } else if self.generics.is_empty() {
+            self.name.clone()
         } else {
-            self.to_string()
+            format!(
+                "{}<{}>",
+                self.name,
+                self.generics_doc_string(options, lookup)
+            )
         }
     }
+
+    fn generics_doc_string(
+        &self,
+        options: &RenderOptions,
+        lookup: &dyn Fn(&TypeInfo) -> Option<String>,
+    ) -> String {
+        self.generics
+            .iter()
+            .map(|generic| generic.to_doc_string(options, lookup))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 }
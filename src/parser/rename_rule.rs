@@ -50,6 +50,69 @@ pub fn get_rename_rule(attributes: &[Attribute]) -> RenameRule {
         .unwrap_or_default()
 }
 
+/// Checks whether the item has a `#[serde(default)]` or `#[serde(default = "...")]` attribute.
+pub fn has_serde_default(attributes: &[Attribute]) -> bool {
+    attributes
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+        .any(|attr| {
+            let mut found = false;
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    found = true;
+                }
+
+                // skip over any value so parsing can continue
+                if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<syn::Expr>()?;
+                }
+
+                Ok(())
+            })
+            .ok();
+
+            found
+        })
+}
+
+/// Gets the name set by a `#[serde(rename = "...")]` attribute on a field or variant.
+/// For `rename(serialize = "...", deserialize = "...")`, the deserialize name is used,
+/// as that is the one users write.
+pub fn get_rename(attributes: &[Attribute]) -> Option<String> {
+    let mut rename = None;
+
+    for attr in attributes
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(Token![=]) {
+                rename = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.input.peek(Token![=]) {
+                // skip over any value so parsing can continue
+                meta.value()?.parse::<syn::Expr>()?;
+            } else if meta.input.peek(syn::token::Paren) {
+                let is_rename = meta.path.is_ident("rename");
+
+                meta.parse_nested_meta(|meta| {
+                    let value = meta.value()?.parse::<LitStr>()?.value();
+                    if is_rename && meta.path.is_ident("deserialize") {
+                        rename = Some(value);
+                    }
+
+                    Ok(())
+                })?;
+            }
+
+            Ok(())
+        })
+        .ok();
+    }
+
+    rename
+}
+
 /*
 Below taken from serde_derive:
 <https://github.com/serde-rs/serde/blob/48aa054f5395d2570f51b9d0c85e486f1b3b46ef/serde_derive/src/internals/case.rs#L21>
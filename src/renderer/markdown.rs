@@ -1,41 +1,221 @@
-use crate::renderer::{RenderOptions, Renderer};
+use crate::parser::replace_intra_doc_links;
+use crate::renderer::{
+    split_summary, type_doc_string, OutputFormat, RenderOptions, Renderer, Slugger,
+};
+use crate::replacer::TocOptions;
+use crate::resolver::{Resolved, Resolver};
+use crate::{FieldInfo, Info};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use std::collections::HashMap;
 use std::fmt::{Result, Write};
+use std::ops::Range;
+
+/// Deepest heading level supported by Markdown.
+pub(crate) const MAX_HEADING_LEVEL: usize = 6;
+
+/// Surrounds the index of a link in the document
+/// until its target is known once finished.
+const LINK_MARKER: char = '\u{E000}';
 
 pub struct MarkdownRenderer<'a> {
     document: String,
     options: &'a RenderOptions,
+    resolver: &'a Resolver,
+    /// Positions in the document to insert
+    /// a table of contents at once finished.
+    tocs: Vec<(usize, TocOptions)>,
+    /// Keys of the items rendered in the document,
+    /// and the position their heading follows.
+    targets: Vec<(usize, usize)>,
+    /// Text and target key of each intra-doc link in the document.
+    links: Vec<(String, usize)>,
+    /// Element whose doc comments are being rendered.
+    scope: Option<&'a Info>,
+}
+
+impl<'a> MarkdownRenderer<'a> {
+    /// Adds a newline if required so that
+    /// the next block has an empty line before it.
+    fn ensure_empty_line(&mut self) -> Result {
+        let second_last_char = self.document.chars().nth_back(2);
+        if !matches!(second_last_char, Some('\n')) {
+            writeln!(self.document)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces intra-doc links which can be resolved with a marker,
+    /// to be replaced with a link to the item once the document is finished.
+    /// Links which cannot be resolved are replaced with their text.
+    fn resolve_links(&mut self, text: &str) -> String {
+        let resolver = self.resolver;
+        let scope = self.scope;
+        let links = &mut self.links;
+
+        replace_intra_doc_links(text, |link| {
+            let Some(resolved) = resolver.resolve_link(&link.target, scope) else {
+                return Some(link.text.to_string());
+            };
+
+            links.push((link.text.to_string(), resolved.key()));
+            Some(format!("{LINK_MARKER}{}{LINK_MARKER}", links.len() - 1))
+        })
+    }
+
+    /// Replaces the link markers in the document
+    /// with links to the anchor of their target,
+    /// or with their text if the target is not in the document.
+    fn finish_links(&self, document: &str, anchors: &HashMap<usize, String>) -> String {
+        let mut output = String::with_capacity(document.len());
+
+        for (i, part) in document.split(LINK_MARKER).enumerate() {
+            // markers surround each link index, so every other part is an index
+            let link = (i % 2 == 1)
+                .then(|| part.parse::<usize>().ok())
+                .flatten()
+                .and_then(|index| self.links.get(index));
+
+            match link {
+                Some((text, key)) => match anchors.get(key) {
+                    Some(slug) => output.push_str(&format!("[{text}](#{slug})")),
+                    None => output.push_str(text),
+                },
+                None => output.push_str(part),
+            }
+        }
+
+        output
+    }
+
+    /// Generates the table of contents for the given headings
+    /// as a nested list of links.
+    fn toc(headings: &[(usize, String, String)], options: TocOptions) -> String {
+        let mut toc = String::new();
+
+        for (level, text, slug) in headings {
+            if *level < options.min_level || *level > options.max_level {
+                continue;
+            }
+
+            let indent = "  ".repeat(level - options.min_level);
+            toc.push_str(&format!("{indent}- [{text}](#{slug})\n"));
+        }
+
+        toc
+    }
 }
 
 impl<'a> Renderer<'a, String> for MarkdownRenderer<'a> {
-    fn new(document: String, options: &'a RenderOptions) -> Self {
-        Self { document, options }
+    fn new(document: String, options: &'a RenderOptions, resolver: &'a Resolver) -> Self {
+        Self {
+            document,
+            options,
+            resolver,
+            tocs: vec![],
+            targets: vec![],
+            links: vec![],
+            scope: None,
+        }
     }
 
     fn options(&self) -> &'a RenderOptions {
         self.options
     }
 
-    fn finish(self) -> String {
-        self.document
+    fn resolver(&self) -> &'a Resolver {
+        self.resolver
     }
 
-    fn render_heading(&mut self, text: &str, depth: usize) -> Result {
-        // always ensure headings have empty line before them
-        let second_last_char = self.document.chars().nth_back(2);
-        if !matches!(second_last_char, Some('\n')) {
-            writeln!(self.document)?;
+    fn finish(mut self) -> String {
+        if self.tocs.is_empty() && self.links.is_empty() {
+            return self.document;
+        }
+
+        let mut slugger = Slugger::new(self.options.slug_style);
+        // links in headings are reduced to their text,
+        // so the slugs and table of contents do not include them
+        let no_anchors = HashMap::new();
+        let headings = find_headings(&self.document)
+            .into_iter()
+            .map(|(position, level, text)| {
+                let text = self.finish_links(text, &no_anchors);
+                let slug = slugger.slug(&text);
+                (position, level, text, slug)
+            })
+            .collect::<Vec<_>>();
+
+        // each item links to the first heading after its target,
+        // using the first place it is rendered
+        let mut anchors = HashMap::new();
+        for &(key, position) in &self.targets {
+            if let Some((.., slug)) = headings.iter().find(|heading| heading.0 >= position) {
+                anchors.entry(key).or_insert_with(|| slug.clone());
+            }
         }
 
+        let headings = headings
+            .into_iter()
+            .map(|(_, level, text, slug)| (level, text, slug))
+            .collect::<Vec<_>>();
+
+        let tocs = self
+            .tocs
+            .iter()
+            .rev()
+            .map(|&(position, options)| (position, Self::toc(&headings, options)))
+            .collect::<Vec<_>>();
+
+        // insert from the end so earlier positions remain valid
+        for (position, toc) in tocs {
+            self.document.insert_str(position, &toc);
+        }
+
+        self.finish_links(&self.document, &anchors)
+    }
+
+    fn format(&self) -> OutputFormat {
+        OutputFormat::Markdown
+    }
+
+    fn render_heading(&mut self, text: &str, depth: usize) -> Result {
+        self.ensure_empty_line()?;
         writeln!(self.document, "{} {}\n", "#".repeat(depth + 1), text)
     }
 
+    fn render_target(&mut self, resolved: Resolved) -> Result {
+        self.targets.push((resolved.key(), self.document.len()));
+        Ok(())
+    }
+
+    fn set_scope(&mut self, scope: &'a Info) {
+        self.scope = Some(scope);
+    }
+
     fn render_description(&mut self, text: &str, depth: usize) -> Result {
-        for line in text.lines() {
-            if let Some(stripped) = line.strip_prefix("# ") {
-                self.render_heading(stripped, depth + 1)?;
+        let text = self.resolve_links(text);
+        let mut last = 0;
+
+        for (range, level, heading) in find_description_headings(&text) {
+            self.document.push_str(&text[last..range.start]);
+
+            // headings in descriptions are relative to the element's own heading
+            let depth = depth + level;
+            if depth < MAX_HEADING_LEVEL {
+                self.render_heading(heading, depth)?;
             } else {
-                writeln!(self.document, "{}", line)?;
-            };
+                self.ensure_empty_line()?;
+                writeln!(self.document, "**{heading}**\n")?;
+            }
+
+            // the heading is already followed by an empty line
+            let rest = &text[range.end..];
+            last = range.end + rest.len() - rest.trim_start_matches(['\r', '\n']).len();
+        }
+
+        let rest = &text[last..];
+        if !rest.is_empty() {
+            writeln!(self.document, "{}", rest.trim_end_matches(['\r', '\n']))?;
         }
 
         Ok(())
@@ -45,7 +225,138 @@ impl<'a> Renderer<'a, String> for MarkdownRenderer<'a> {
         writeln!(self.document, "> Type: `{}`\n", text)
     }
 
+    fn render_value(&mut self, value: &str) -> Result {
+        writeln!(self.document, "> Value: `{}`\n", value)
+    }
+
+    fn render_since(&mut self, version: &str) -> Result {
+        writeln!(self.document, "> Since: `{}`\n", version)
+    }
+
     fn render_text(&mut self, text: &str) -> Result {
         write!(self.document, "{}", text)
     }
+
+    fn render_block(&mut self, text: &str) -> Result {
+        let text = self.resolve_links(text);
+
+        self.ensure_empty_line()?;
+        writeln!(self.document, "{}", text.trim_end())
+    }
+
+    fn render_field_table(&mut self, fields: &[&FieldInfo]) -> Result {
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        self.ensure_empty_line()?;
+
+        writeln!(self.document, "| Name | Type | Default | Description |")?;
+        writeln!(self.document, "|------|------|---------|-------------|")?;
+
+        for field in fields {
+            let (summary, _) = split_summary(&field.description);
+            let summary = self.resolve_links(&summary);
+            let default = field.default.as_deref().unwrap_or_default();
+
+            writeln!(
+                self.document,
+                "| `{}` | `{}` | {} | {} |",
+                field.name,
+                escape_cell(&type_doc_string(&field.ty, self.options, self.resolver)),
+                escape_cell(default),
+                escape_cell(&summary),
+            )?;
+        }
+
+        writeln!(self.document)
+    }
+
+    fn render_toc(&mut self, options: TocOptions) -> Result {
+        self.tocs.push((self.document.len(), options));
+        Ok(())
+    }
+
+    fn render_details(&mut self, summary: &str, text: &str, depth: usize) -> Result {
+        writeln!(
+            self.document,
+            "<details>\n<summary><code>{summary}</code></summary>\n"
+        )?;
+        self.render_description(text, depth)?;
+        writeln!(self.document, "\n</details>\n")
+    }
+
+    fn skip_code(&self) -> bool {
+        true
+    }
+}
+
+/// Escapes characters which would break a table cell.
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Parses a description as CommonMark to find its ATX and setext headings,
+/// returning the source range, level and inline text of each.
+pub fn find_description_headings(text: &str) -> Vec<(Range<usize>, usize, &str)> {
+    let mut headings = vec![];
+    // range, level and the range of the inline content so far
+    let mut current: Option<(Range<usize>, usize, Option<Range<usize>>)> = None;
+
+    for (event, range) in Parser::new(text).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some((range, level as usize, None));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((range, level, content)) = current.take() {
+                    let content = content.map_or("", |content| text[content].trim());
+                    headings.push((range, level, content));
+                }
+            }
+            _ => {
+                if let Some((_, _, content)) = &mut current {
+                    let start = content.as_ref().map_or(range.start, |c| c.start);
+                    *content = Some(start..range.end);
+                }
+            }
+        }
+    }
+
+    headings
+}
+
+/// Finds all ATX headings in the document which are not inside code blocks,
+/// returning the position, level and text of each.
+fn find_headings(document: &str) -> Vec<(usize, usize, &str)> {
+    let mut headings = vec![];
+    let mut fence: Option<&str> = None;
+    let mut position = 0;
+
+    for line in document.split_inclusive('\n') {
+        let start = position;
+        position += line.len();
+
+        let trimmed = line.trim_start().trim_end_matches(['\r', '\n']);
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with([' ', '\t']) {
+            let text = trimmed[level..].trim().trim_end_matches('#').trim_end();
+            headings.push((start, level, text));
+        }
+    }
+
+    headings
 }
@@ -1,68 +1,422 @@
 mod markdown;
+mod slug;
+mod templates;
 
-use crate::replacer::ReplaceOptions;
-use crate::{ElementInfo, EnumInfo, FieldInfo, Info, StructInfo};
-pub use markdown::MarkdownRenderer;
+use crate::replacer::{Layout, ReplaceOptions, SortOrder, TocOptions};
+use crate::resolver::{Resolved, Resolver};
+use crate::{AliasInfo, ElementInfo, EnumInfo, FieldInfo, Info, StructInfo, TypeInfo, VariantInfo};
+pub(crate) use markdown::MAX_HEADING_LEVEL;
+pub use markdown::{find_description_headings, MarkdownRenderer};
+use serde::Deserialize;
+pub use slug::{SlugStyle, Slugger};
+use std::collections::HashMap;
 use std::fmt::{Result, Write};
+pub use templates::ElementTemplates;
 
 #[derive(Debug)]
 pub struct RenderOptions {
     pub simplified_types: bool,
+    pub type_names: HashMap<String, String>,
+    pub slug_style: SlugStyle,
+}
+
+/// The format documentation is rendered as.
+#[derive(Deserialize, clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Markdown (CommonMark)
+    #[default]
+    Markdown,
+}
+
+impl OutputFormat {
+    /// Gets the name of the format, as used in config and conditions.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "markdown",
+        }
+    }
+
+    /// Gets the file extension used for the format, without the leading dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "md",
+        }
+    }
 }
 
 pub trait Renderer<'a, W: Write> {
-    fn new(document: W, options: &'a RenderOptions) -> Self;
+    fn new(document: W, options: &'a RenderOptions, resolver: &'a Resolver) -> Self;
     fn options(&self) -> &'a RenderOptions;
+    fn resolver(&self) -> &'a Resolver;
     fn finish(self) -> W;
 
+    /// The format this renderer outputs.
+    fn format(&self) -> OutputFormat;
+
     fn render_heading(&mut self, text: &str, depth: usize) -> Result;
+
+    /// Marks the next heading as the one documenting the item,
+    /// so intra-doc links to the item can point to it.
+    fn render_target(&mut self, resolved: Resolved) -> Result;
+
+    /// Sets the element whose doc comments are being rendered,
+    /// so that relative intra-doc links in them can be resolved.
+    fn set_scope(&mut self, scope: &'a Info);
+
     fn render_description(&mut self, text: &str, depth: usize) -> Result;
     fn render_type(&mut self, text: &str) -> Result;
+    fn render_value(&mut self, value: &str) -> Result;
+    fn render_since(&mut self, version: &str) -> Result;
     fn render_text(&mut self, text: &str) -> Result;
 
+    /// Renders pre-formatted text, such as the output of a custom template,
+    /// as a block separated from the surrounding content.
+    fn render_block(&mut self, text: &str) -> Result;
+
+    /// Renders the fields as a table with name, type, default and description columns.
+    /// Only the summary of each description should be included.
+    fn render_field_table(&mut self, fields: &[&FieldInfo]) -> Result;
+
+    /// Renders a table of contents at the current position.
+    /// As it depends on the rest of the document,
+    /// it is not generated until the document is finished.
+    fn render_toc(&mut self, options: TocOptions) -> Result;
+
+    /// Renders a collapsible section containing further details.
+    fn render_details(&mut self, summary: &str, text: &str, depth: usize) -> Result;
+
+    /// Whether directives inside code blocks and inline code
+    /// should be left untouched.
+    fn skip_code(&self) -> bool {
+        false
+    }
+
+    fn render_resolved(&mut self, resolved: Resolved, options: ReplaceOptions) -> Result {
+        match resolved {
+            Resolved::Element(info) => self.render_element(info, options),
+            Resolved::Field(info) if options.header => self.render_field(info, options.depth),
+            Resolved::Field(info) => {
+                self.render_type(&type_doc_string(&info.ty, self.options(), self.resolver()))?;
+                self.render_description(&info.description, options.depth)
+            }
+            Resolved::Variant(info) if options.header => self.render_variant(info, options.depth),
+            Resolved::Variant(info) => {
+                self.render_description(&info.description, options.depth)?;
+                self.render_variant_fields(info, options.depth + 1)
+            }
+        }
+    }
+
     fn render_element(&mut self, info: &Info, options: ReplaceOptions) -> Result {
         let depth = options.depth;
 
         if options.header {
+            self.render_target(Resolved::Element(info))?;
             self.render_heading(&info.name, depth)?;
         }
 
+        // newtypes and aliases are documented as a value of the type they wrap
+        if let Some(ty) = info.element.value_type() {
+            self.render_type(&type_doc_string(ty, self.options(), self.resolver()))?;
+        }
+
+        if let Some(value) = info.element.value() {
+            self.render_value(value)?;
+        }
+
+        if let Some(since) = &info.since {
+            self.render_since(since)?;
+        }
+
         self.render_description(&info.description, depth)?;
 
         match &info.element {
-            ElementInfo::Struct(info) => self.render_struct(info, depth + 1),
-            ElementInfo::Enum(info) => self.render_enum(info, depth + 1),
+            ElementInfo::Struct(element) => match element.newtype_field() {
+                // the wrapped field usually describes constraints on the value
+                Some(field) if !field.description.trim().is_empty() => {
+                    if !info.description.trim().is_empty() {
+                        self.render_text("\n")?;
+                    }
+                    self.render_description(&field.description, depth)
+                }
+                Some(_) => Ok(()),
+                None => self.render_struct(element, &options, depth + 1),
+            },
+            ElementInfo::Enum(element) => self.render_enum(element, &options, depth + 1),
+            ElementInfo::Alias(element) => self.render_alias(info, element, &options, depth + 1),
+            ElementInfo::Const(_) | ElementInfo::Static(_) => Ok(()),
         }?;
 
         Ok(())
     }
 
-    fn render_struct(&mut self, info: &StructInfo, depth: usize) -> Result {
-        for field in &info.fields {
-            self.render_field(field, depth)?;
+    /// Renders the contents of the aliased type, if it is a struct or enum in the crate.
+    /// Its description is used if the alias does not have one.
+    fn render_alias(
+        &mut self,
+        info: &Info,
+        alias: &AliasInfo,
+        options: &ReplaceOptions,
+        depth: usize,
+    ) -> Result {
+        let Some(target) = self.resolver().resolve_type(&alias.ty) else {
+            return Ok(());
+        };
+
+        // links in the target's doc comments are relative to the target
+        self.set_scope(target);
+
+        if info.description.trim().is_empty() {
+            self.render_description(&target.description, depth - 1)?;
+        }
+
+        match &target.element {
+            ElementInfo::Struct(element) => self.render_struct(element, options, depth),
+            ElementInfo::Enum(element) => self.render_enum(element, options, depth),
+            ElementInfo::Alias(_) | ElementInfo::Const(_) | ElementInfo::Static(_) => Ok(()),
+        }
+    }
+
+    fn render_struct(
+        &mut self,
+        info: &StructInfo,
+        options: &ReplaceOptions,
+        depth: usize,
+    ) -> Result {
+        let fields = select_fields(info, options);
+
+        if !options.group {
+            return self.render_fields(&fields, options, depth);
+        }
+
+        let (ungrouped, groups) = group_by(fields, |f| f.group.as_deref());
+
+        self.render_fields(&ungrouped, options, depth)?;
+
+        for (group, fields) in groups {
+            self.render_heading(group, depth)?;
+            self.render_fields(&fields, options, depth + 1)?;
         }
 
         Ok(())
     }
 
-    fn render_enum(&mut self, info: &EnumInfo, depth: usize) -> Result {
-        for variant in &info.variants {
-            self.render_heading(&variant.name, depth)?;
-            self.render_description(&variant.description, depth)?;
+    fn render_fields(
+        &mut self,
+        fields: &[&FieldInfo],
+        options: &ReplaceOptions,
+        depth: usize,
+    ) -> Result {
+        match options.layout {
+            Layout::Headings => {
+                for field in fields {
+                    self.render_field(field, depth)?;
+                }
+            }
+            Layout::Table => {
+                self.render_field_table(fields)?;
+
+                if options.details {
+                    for field in fields {
+                        if let (_, Some(details)) = split_summary(&field.description) {
+                            self.render_details(&field.name, details, depth)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_enum(&mut self, info: &EnumInfo, options: &ReplaceOptions, depth: usize) -> Result {
+        let variants = select_variants(info, options);
+
+        let (ungrouped, groups) = if options.group {
+            group_by(variants, |v| v.group.as_deref())
+        } else {
+            (variants, vec![])
+        };
 
-            for field in &variant.fields {
-                self.render_field(field, depth + 1)?;
+        for variant in ungrouped {
+            self.render_variant(variant, depth)?;
+        }
+
+        for (group, variants) in groups {
+            self.render_heading(group, depth)?;
+
+            for variant in variants {
+                self.render_variant(variant, depth + 1)?;
             }
         }
 
         Ok(())
     }
 
+    fn render_variant(&mut self, info: &VariantInfo, depth: usize) -> Result {
+        self.render_target(Resolved::Variant(info))?;
+        self.render_heading(&info.name, depth)?;
+
+        if let Some(discriminant) = &info.discriminant {
+            self.render_value(discriminant)?;
+        }
+
+        if let Some(since) = &info.since {
+            self.render_since(since)?;
+        }
+
+        self.render_description(&info.description, depth)?;
+        self.render_variant_fields(info, depth + 1)
+    }
+
+    fn render_variant_fields(&mut self, info: &VariantInfo, depth: usize) -> Result {
+        for field in &info.fields {
+            self.render_field(field, depth)?;
+        }
+
+        Ok(())
+    }
+
     fn render_field(&mut self, info: &FieldInfo, depth: usize) -> Result {
+        self.render_target(Resolved::Field(info))?;
         self.render_heading(&info.name, depth)?;
-        self.render_type(&info.ty.to_doc_string(self.options().simplified_types))?;
+        self.render_type(&type_doc_string(&info.ty, self.options(), self.resolver()))?;
+
+        if let Some(since) = &info.since {
+            self.render_since(since)?;
+        }
+
         self.render_description(&info.description, depth)?;
 
         Ok(())
     }
 }
+
+/// Formats a type for display.
+/// Unit-only enums in the crate are shown as the values they accept, such as `'debug' | 'info'`,
+/// and other types in the crate by their display name.
+pub fn type_doc_string(ty: &TypeInfo, options: &RenderOptions, resolver: &Resolver) -> String {
+    ty.to_doc_string(options, &|ty| {
+        let info = resolver.resolve_type(ty)?;
+
+        enum_values(info).or_else(|| {
+            (ty.generics.is_empty() && info.name != info.ident).then(|| info.name.clone())
+        })
+    })
+}
+
+/// Gets the values accepted for a unit-only enum, separated by `|`.
+/// Enums deriving `serde_repr` traits accept their discriminants,
+/// and others accept the names of their variants.
+fn enum_values(info: &Info) -> Option<String> {
+    let ElementInfo::Enum(element) = &info.element else {
+        return None;
+    };
+
+    if !element.is_unit_only() {
+        return None;
+    }
+
+    let is_repr = info
+        .derives
+        .iter()
+        .any(|derive| derive == "Serialize_repr" || derive == "Deserialize_repr");
+
+    let values = if is_repr {
+        // variants without a discriminant are one more than the previous
+        let mut next = 0;
+        element
+            .variants
+            .iter()
+            .map(|variant| {
+                let value = match &variant.discriminant {
+                    Some(discriminant) => discriminant.parse::<i128>().ok()?,
+                    None => next,
+                };
+                next = value + 1;
+                Some(value.to_string())
+            })
+            .collect::<Option<Vec<_>>>()?
+    } else {
+        element
+            .variants
+            .iter()
+            .map(|variant| format!("'{}'", variant.name))
+            .collect()
+    };
+
+    Some(values.join(" | "))
+}
+
+/// Gets the struct fields which pass the filters, in the configured order.
+pub fn select_fields<'a>(info: &'a StructInfo, options: &ReplaceOptions) -> Vec<&'a FieldInfo> {
+    let mut fields = info
+        .fields
+        .iter()
+        .filter(|f| options.includes_field(f))
+        .collect::<Vec<_>>();
+
+    match options.sort {
+        SortOrder::Source => {}
+        SortOrder::Name => fields.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortOrder::RequiredFirst => fields.sort_by_key(|f| !f.required),
+    }
+
+    fields
+}
+
+/// Gets the enum variants which pass the filters, in the configured order.
+/// Variants are never required, so `required-first` keeps the source order.
+pub fn select_variants<'a>(info: &'a EnumInfo, options: &ReplaceOptions) -> Vec<&'a VariantInfo> {
+    let mut variants = info
+        .variants
+        .iter()
+        .filter(|v| options.includes_variant(v))
+        .collect::<Vec<_>>();
+
+    if options.sort == SortOrder::Name {
+        variants.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    variants
+}
+
+/// Splits a description into its first paragraph,
+/// with lines joined onto a single line,
+/// and the remaining text if there is any.
+pub fn split_summary(text: &str) -> (String, Option<&str>) {
+    let text = text.trim();
+
+    let (summary, rest) = match text.split_once("\n\n") {
+        Some((summary, rest)) => (summary, Some(rest.trim()).filter(|rest| !rest.is_empty())),
+        None => (text, None),
+    };
+
+    let summary = summary.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+
+    (summary, rest)
+}
+
+/// Splits items into those without a group,
+/// and those with a group, keyed by the group name.
+/// Groups are returned in the order they first appear.
+fn group_by<T>(
+    items: Vec<&T>,
+    group: impl Fn(&T) -> Option<&str>,
+) -> (Vec<&T>, Vec<(&str, Vec<&T>)>) {
+    let mut ungrouped = vec![];
+    let mut groups: Vec<(&str, Vec<&T>)> = vec![];
+
+    for item in items {
+        match group(item) {
+            Some(name) => match groups.iter_mut().find(|(group, _)| *group == name) {
+                Some((_, items)) => items.push(item),
+                None => groups.push((name, vec![item])),
+            },
+            None => ungrouped.push(item),
+        }
+    }
+
+    (ungrouped, groups)
+}
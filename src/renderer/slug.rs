@@ -0,0 +1,96 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The algorithm used to generate heading anchors,
+/// which should match the tool the output is viewed with.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugStyle {
+    /// GitHub and most tools based on it.
+    #[default]
+    Github,
+    /// GitLab, which collapses repeated hyphens.
+    Gitlab,
+    /// mdBook, which ignores inline formatting and HTML tags.
+    Mdbook,
+}
+
+impl SlugStyle {
+    /// Converts heading text into the anchor id for this style.
+    /// This does not make the slug unique, see [`Slugger`].
+    pub fn slugify(self, text: &str) -> String {
+        let text = match self {
+            SlugStyle::Mdbook => strip_tags(text),
+            _ => text.to_string(),
+        };
+
+        let slug = text
+            .trim()
+            .chars()
+            .filter_map(|c| match c {
+                c if c.is_whitespace() => Some('-'),
+                '-' | '_' => Some(c),
+                c if c.is_alphanumeric() => Some(c),
+                _ => None,
+            })
+            .flat_map(char::to_lowercase)
+            .collect::<String>();
+
+        match self {
+            SlugStyle::Gitlab => slug
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-"),
+            _ => slug,
+        }
+    }
+}
+
+/// Generates unique slugs for a document,
+/// adding a numbered suffix to repeated headings.
+#[derive(Debug)]
+pub struct Slugger {
+    style: SlugStyle,
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub fn new(style: SlugStyle) -> Self {
+        Self {
+            style,
+            seen: HashMap::new(),
+        }
+    }
+
+    pub fn slug(&mut self, text: &str) -> String {
+        let slug = self.style.slugify(text);
+
+        let count = self.seen.entry(slug.clone()).or_insert(0);
+        let unique = if *count == 0 {
+            slug
+        } else {
+            format!("{slug}-{count}")
+        };
+
+        *count += 1;
+        unique
+    }
+}
+
+/// Removes any HTML tags from the text.
+fn strip_tags(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+
+    output
+}
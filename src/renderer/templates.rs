@@ -0,0 +1,278 @@
+use std::path::Path;
+
+use color_eyre::eyre::WrapErr;
+use color_eyre::Result;
+use minijinja::{path_loader, Environment, ErrorKind, State, Template};
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::renderer::{
+    select_fields, select_variants, split_summary, type_doc_string, OutputFormat, RenderOptions,
+};
+use crate::replacer::ReplaceOptions;
+use crate::resolver::{Resolved, Resolver};
+use crate::{ElementInfo, FieldInfo, Info, VariantInfo, Visibility};
+
+/// User-provided MiniJinja templates used to render elements
+/// in place of the built-in layout.
+///
+/// Templates are named after the kind of element they render
+/// (`struct`, `enum`, `alias`, `const`, `static`, `field` or `variant`)
+/// with the format's file extension, for example `struct.md`.
+/// Field and variant templates are only used when one is injected directly,
+/// not for those rendered as part of an element using the built-in layout.
+/// Templates in the root of the directory are used by default.
+/// Named sets of templates can be placed in sub-directories,
+/// which are selected using the `template` injection option.
+pub struct ElementTemplates {
+    env: Environment<'static>,
+    extension: &'static str,
+}
+
+impl ElementTemplates {
+    pub fn new(path: &Path, format: OutputFormat) -> Self {
+        let mut env = Environment::new();
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+        env.add_function("heading", heading);
+
+        if path.is_dir() {
+            debug!("Loading element templates from {}", path.display());
+            env.set_loader(path_loader(path));
+        }
+
+        Self {
+            env,
+            extension: format.extension(),
+        }
+    }
+
+    /// Renders the element using a matching template.
+    ///
+    /// Returns `None` if there is no template for the element,
+    /// in which case the built-in layout should be used.
+    pub fn render(
+        &self,
+        resolved: Resolved,
+        options: &ReplaceOptions,
+        render_options: &RenderOptions,
+        resolver: &Resolver,
+    ) -> Result<Option<String>> {
+        let kind = match resolved {
+            Resolved::Element(info) => info.element.kind(),
+            Resolved::Field(_) => "field",
+            Resolved::Variant(_) => "variant",
+        };
+
+        let Some(template) = self.find(kind, options.template.as_deref())? else {
+            return Ok(None);
+        };
+
+        let depth = options.depth;
+
+        let output = match resolved {
+            Resolved::Element(info) => template.render(minijinja::context! {
+                element => ElementContext::new(info, options, render_options, resolver),
+                options,
+                depth,
+            }),
+            Resolved::Field(info) => template.render(minijinja::context! {
+                field => FieldContext::new(info, render_options, resolver),
+                options,
+                depth,
+            }),
+            Resolved::Variant(info) => template.render(minijinja::context! {
+                variant => VariantContext::new(info, render_options, resolver),
+                options,
+                depth,
+            }),
+        }
+        .wrap_err_with(|| format!("Failed to render template '{}'", template.name()))?;
+
+        Ok(Some(output))
+    }
+
+    /// Finds the template for the kind of element,
+    /// checking the named set first if given.
+    fn find(&self, kind: &str, set: Option<&str>) -> Result<Option<Template<'_, '_>>> {
+        let file_name = format!("{kind}.{}", self.extension);
+
+        let mut names = vec![];
+        if let Some(set) = set {
+            names.push(format!("{set}/{file_name}"));
+        }
+        names.push(file_name);
+
+        for name in &names {
+            match self.env.get_template(name) {
+                Ok(template) => return Ok(Some(template)),
+                Err(err) if err.kind() == ErrorKind::TemplateNotFound => {}
+                Err(err) => {
+                    return Err(err).wrap_err_with(|| format!("Invalid template '{name}'"));
+                }
+            }
+        }
+
+        if let Some(set) = set {
+            warn!("No '{kind}' template found in set '{set}', using built-in layout");
+        }
+
+        Ok(None)
+    }
+}
+
+/// Template function which creates a Markdown heading
+/// at the current depth, plus an optional offset.
+///
+/// For example, `{{ heading(element.name) }}` or `{{ heading(field.name, 1) }}`.
+fn heading(state: &State, text: String, offset: Option<usize>) -> String {
+    let depth = state
+        .lookup("depth")
+        .and_then(|depth| usize::try_from(depth).ok())
+        .unwrap_or_default();
+
+    format!(
+        "{} {text}",
+        "#".repeat(depth + offset.unwrap_or_default() + 1)
+    )
+}
+
+/// An element, as passed to templates.
+#[derive(Serialize)]
+struct ElementContext<'a> {
+    ident: &'a str,
+    name: &'a str,
+    /// `struct`, `enum`, `alias`, `const` or `static`.
+    kind: &'static str,
+    /// For newtype structs, type aliases, constants and statics, the type of their value.
+    #[serde(rename = "type")]
+    ty: Option<String>,
+    /// For constants and statics, the value if known.
+    value: Option<&'a str>,
+    description: &'a str,
+    /// The first paragraph of the description.
+    summary: String,
+    since: Option<&'a str>,
+    derives: &'a [String],
+    implements: &'a [String],
+    /// For structs, the fields which pass the filters, in order.
+    fields: Vec<FieldContext<'a>>,
+    /// For enums, the variants which pass the filters, in order.
+    variants: Vec<VariantContext<'a>>,
+}
+
+impl<'a> ElementContext<'a> {
+    fn new(
+        info: &'a Info,
+        options: &ReplaceOptions,
+        render_options: &RenderOptions,
+        resolver: &Resolver,
+    ) -> Self {
+        let (fields, variants) = match &info.element {
+            ElementInfo::Struct(element) => (
+                select_fields(element, options)
+                    .into_iter()
+                    .map(|field| FieldContext::new(field, render_options, resolver))
+                    .collect(),
+                vec![],
+            ),
+            ElementInfo::Enum(element) => (
+                vec![],
+                select_variants(element, options)
+                    .into_iter()
+                    .map(|variant| VariantContext::new(variant, render_options, resolver))
+                    .collect(),
+            ),
+            ElementInfo::Alias(_) | ElementInfo::Const(_) | ElementInfo::Static(_) => {
+                (vec![], vec![])
+            }
+        };
+
+        Self {
+            ident: &info.ident,
+            name: &info.name,
+            kind: info.element.kind(),
+            ty: info
+                .element
+                .value_type()
+                .map(|ty| type_doc_string(ty, render_options, resolver)),
+            value: info.element.value(),
+            description: &info.description,
+            summary: split_summary(&info.description).0,
+            since: info.since.as_deref(),
+            derives: &info.derives,
+            implements: &info.implements,
+            fields,
+            variants,
+        }
+    }
+}
+
+/// A struct or variant field, as passed to templates.
+#[derive(Serialize)]
+struct FieldContext<'a> {
+    ident: &'a str,
+    name: &'a str,
+    description: &'a str,
+    /// The first paragraph of the description.
+    summary: String,
+    /// The type, formatted for display.
+    #[serde(rename = "type")]
+    ty: String,
+    visibility: Visibility,
+    since: Option<&'a str>,
+    default: Option<&'a str>,
+    required: bool,
+    group: Option<&'a str>,
+}
+
+impl<'a> FieldContext<'a> {
+    fn new(info: &'a FieldInfo, render_options: &RenderOptions, resolver: &Resolver) -> Self {
+        Self {
+            ident: &info.ident,
+            name: &info.name,
+            description: &info.description,
+            summary: split_summary(&info.description).0,
+            ty: type_doc_string(&info.ty, render_options, resolver),
+            visibility: info.visibility,
+            since: info.since.as_deref(),
+            default: info.default.as_deref(),
+            required: info.required,
+            group: info.group.as_deref(),
+        }
+    }
+}
+
+/// An enum variant, as passed to templates.
+#[derive(Serialize)]
+struct VariantContext<'a> {
+    ident: &'a str,
+    name: &'a str,
+    description: &'a str,
+    /// The first paragraph of the description.
+    summary: String,
+    /// Explicit discriminant value, if it is a literal.
+    discriminant: Option<&'a str>,
+    since: Option<&'a str>,
+    group: Option<&'a str>,
+    fields: Vec<FieldContext<'a>>,
+}
+
+impl<'a> VariantContext<'a> {
+    fn new(info: &'a VariantInfo, render_options: &RenderOptions, resolver: &Resolver) -> Self {
+        Self {
+            ident: &info.ident,
+            name: &info.name,
+            description: &info.description,
+            summary: split_summary(&info.description).0,
+            discriminant: info.discriminant.as_deref(),
+            since: info.since.as_deref(),
+            group: info.group.as_deref(),
+            fields: info
+                .fields
+                .iter()
+                .map(|field| FieldContext::new(field, render_options, resolver))
+                .collect(),
+        }
+    }
+}
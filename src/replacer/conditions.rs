@@ -0,0 +1,156 @@
+use color_eyre::eyre::{eyre, Report};
+use color_eyre::Result;
+use syn::{BinOp, Expr, ExprLit, Lit, UnOp};
+
+/// Values conditions are evaluated against.
+pub struct ConditionContext<'a> {
+    /// Enabled crate features.
+    pub features: &'a [String],
+    /// Name of the output format.
+    pub format: &'a str,
+}
+
+/// Parses and evaluates a condition, for example `feature("wayland") && !format("html")`.
+///
+/// The supported functions are `feature("name")` and `format("name")`,
+/// which can be combined using `!`, `&&`, `||` and parentheses.
+pub fn evaluate(condition: &str, context: &ConditionContext) -> Result<bool> {
+    let expr = syn::parse_str::<Expr>(condition)
+        .map_err(|err| eyre!("Invalid condition '{condition}': {err}"))?;
+
+    evaluate_expr(&expr, context)
+}
+
+fn evaluate_expr(expr: &Expr, context: &ConditionContext) -> Result<bool> {
+    match expr {
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Not(_)) => {
+            Ok(!evaluate_expr(&unary.expr, context)?)
+        }
+        Expr::Binary(binary) => match binary.op {
+            BinOp::And(_) => {
+                Ok(evaluate_expr(&binary.left, context)? && evaluate_expr(&binary.right, context)?)
+            }
+            BinOp::Or(_) => {
+                Ok(evaluate_expr(&binary.left, context)? || evaluate_expr(&binary.right, context)?)
+            }
+            _ => Err(unsupported()),
+        },
+        Expr::Paren(paren) => evaluate_expr(&paren.expr, context),
+        Expr::Group(group) => evaluate_expr(&group.expr, context),
+        Expr::Lit(ExprLit {
+            lit: Lit::Bool(bool),
+            ..
+        }) => Ok(bool.value),
+        Expr::Call(call) => {
+            let Expr::Path(func) = call.func.as_ref() else {
+                return Err(unsupported());
+            };
+
+            let arg = match call.args.iter().collect::<Vec<_>>()[..] {
+                [Expr::Lit(ExprLit {
+                    lit: Lit::Str(str), ..
+                })] => str.value(),
+                _ => return Err(eyre!("Expected a single string argument in condition")),
+            };
+
+            if func.path.is_ident("feature") {
+                Ok(context.features.contains(&arg))
+            } else if func.path.is_ident("format") {
+                Ok(context.format == arg)
+            } else {
+                Err(eyre!(
+                    "Unknown condition function, expected `feature` or `format`"
+                ))
+            }
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+fn unsupported() -> Report {
+    eyre!("Unsupported condition expression, expected a function call, `!`, `&&` or `||`")
+}
+
+/// An open `@if` block.
+struct Block {
+    /// Whether the current branch of the block is output.
+    active: bool,
+    /// Whether any branch of the block has been taken,
+    /// in which case later branches are skipped.
+    taken: bool,
+    /// Whether an `@else` has been seen.
+    has_else: bool,
+    /// Location of the opening `@if`, for errors.
+    location: String,
+}
+
+/// Tracks the nested `@if` blocks open in a single template.
+#[derive(Default)]
+pub struct Conditions {
+    blocks: Vec<Block>,
+}
+
+impl Conditions {
+    /// Whether content at the current position should be output.
+    pub fn is_active(&self) -> bool {
+        self.blocks.last().is_none_or(|block| block.active)
+    }
+
+    /// Opens a new block.
+    /// The condition is only evaluated if the enclosing block is active,
+    /// so invalid conditions in skipped blocks are not reported.
+    pub fn push_if<F>(&mut self, evaluate: F, location: String) -> Result<()>
+    where
+        F: FnOnce() -> Result<bool>,
+    {
+        let parent_active = self.is_active();
+        let active = parent_active && evaluate()?;
+
+        self.blocks.push(Block {
+            active,
+            // a skipped parent means no branch can be taken
+            taken: active || !parent_active,
+            has_else: false,
+            location,
+        });
+
+        Ok(())
+    }
+
+    /// Switches the innermost block to its `@else` branch.
+    pub fn push_else(&mut self, location: &str) -> Result<()> {
+        let block = self
+            .blocks
+            .last_mut()
+            .ok_or_else(|| eyre!("Unexpected @else without matching @if at {location}"))?;
+
+        if block.has_else {
+            return Err(eyre!(
+                "Duplicate @else for @if at {} found at {location}",
+                block.location
+            ));
+        }
+
+        block.has_else = true;
+        block.active = !block.taken;
+        block.taken = true;
+
+        Ok(())
+    }
+
+    /// Closes the innermost block.
+    pub fn pop(&mut self, location: &str) -> Result<()> {
+        self.blocks
+            .pop()
+            .map(|_| ())
+            .ok_or_else(|| eyre!("Unexpected @endif without matching @if at {location}"))
+    }
+
+    /// Checks all blocks have been closed.
+    pub fn finish(self) -> Result<()> {
+        match self.blocks.last() {
+            Some(block) => Err(eyre!("Unclosed @if at {}", block.location)),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use color_eyre::eyre::{eyre, WrapErr};
+use color_eyre::Result;
+use globset::Glob;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::renderer::{ElementTemplates, Renderer};
+use crate::replacer::conditions::{ConditionContext, Conditions};
+use crate::replacer::tokenizer::{Token, TokenKind, Tokenizer};
+use crate::resolver::Resolved;
+use crate::resolver::Resolver;
+use crate::variables::Variables;
+use crate::{ElementInfo, Info};
+
+pub use options::{
+    parse_options, EachOptions, IncludeOptions, Layout, ReplaceOptions, SortOrder, TocOptions,
+};
+
+mod conditions;
+mod options;
+mod tokenizer;
+
+/// Shared state used to process every template.
+pub struct ReplaceContext<'a> {
+    pub resolver: &'a Resolver,
+    /// Default options for every injection.
+    pub defaults: &'a ReplaceOptions,
+    pub variables: &'a Variables,
+    /// Custom templates to render elements with.
+    pub templates: &'a ElementTemplates,
+    /// Whether to fail on unknown variables, instead of logging a warning.
+    pub strict: bool,
+}
+
+pub struct Replacer<'a, R, W>
+where
+    R: Renderer<'a, W>,
+    W: Write,
+{
+    renderer: R,
+    context: &'a ReplaceContext<'a>,
+    /// Canonical paths of the files currently being processed,
+    /// with the innermost include last.
+    file_stack: Vec<PathBuf>,
+    /// Number of levels to shift headings by,
+    /// from the depth of any includes being processed.
+    depth_offset: usize,
+    /// Every element, field and variant rendered so far,
+    /// with the heading depth each was rendered at.
+    injected: Vec<(Resolved<'a>, usize)>,
+    _phantom: PhantomData<W>,
+}
+
+impl<'a, R, W> Replacer<'a, R, W>
+where
+    R: Renderer<'a, W>,
+    W: Write,
+{
+    pub fn new(renderer: R, context: &'a ReplaceContext<'a>) -> Self {
+        Self {
+            renderer,
+            context,
+            file_stack: vec![],
+            depth_offset: 0,
+            injected: vec![],
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Reads and processes the template file at the given path.
+    pub fn replace_file(&mut self, path: &Path) -> Result<()> {
+        let canonical = path.canonicalize()?;
+
+        if self.file_stack.contains(&canonical) {
+            let cycle = self
+                .file_stack
+                .iter()
+                .chain([&canonical])
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+
+            return Err(eyre!("Include cycle detected: {cycle}"));
+        }
+
+        let input = fs::read_to_string(path)?;
+
+        self.file_stack.push(canonical);
+        let res = self
+            .replace(&input)
+            .wrap_err_with(|| format!("Failed to render '{}'", path.display()));
+        self.file_stack.pop();
+
+        res
+    }
+
+    /// Processes the template, rendering static text
+    /// and replacing any directives as they are encountered.
+    pub fn replace(&mut self, input: &str) -> Result<()> {
+        let mut conditions = Conditions::default();
+        // whether to drop the newline after a conditional directive on its own line
+        let mut trim_newline = false;
+
+        for token in Tokenizer::new(input, self.renderer.skip_code()) {
+            let mut token = token?;
+
+            if std::mem::take(&mut trim_newline) && token.kind == TokenKind::Text {
+                token.text = token.text.strip_prefix('\n').unwrap_or(token.text);
+            }
+
+            if token.kind == TokenKind::Directive {
+                if let Some((name @ ("if" | "else" | "endif"), args)) = parse_command(token.text) {
+                    self.replace_conditional(name, args, token, &mut conditions)?;
+                    trim_newline = token.span.column == 1;
+                    continue;
+                }
+            }
+
+            if !conditions.is_active() {
+                continue;
+            }
+
+            match token.kind {
+                TokenKind::Text if token.span.column == 1 => {
+                    let text = shift_heading(token.text, self.depth_offset);
+                    self.renderer.render_text(&text)?
+                }
+                TokenKind::Text | TokenKind::Code => self.renderer.render_text(token.text)?,
+                TokenKind::Directive => self.replace_directive(token, input)?,
+            }
+        }
+
+        conditions.finish()
+    }
+
+    fn replace_directive(&mut self, token: Token, input: &str) -> Result<()> {
+        let source = &input[token.span.start..token.span.end];
+
+        match parse_command(token.text) {
+            Some((name, args)) => self.replace_command(name, args, token, source),
+            None => self.replace_injection(token.text.trim(), token, source),
+        }
+    }
+
+    /// Handles an `@if`, `@else` or `@endif` directive.
+    fn replace_conditional(
+        &self,
+        name: &str,
+        args: &str,
+        token: Token,
+        conditions: &mut Conditions,
+    ) -> Result<()> {
+        let location = self.location(token);
+
+        match name {
+            "if" => {
+                let context = ConditionContext {
+                    features: self.context.resolver.features(),
+                    format: self.renderer.format().name(),
+                };
+
+                let evaluate = || {
+                    conditions::evaluate(args, &context)
+                        .wrap_err_with(|| format!("Invalid condition at {location}"))
+                };
+
+                conditions.push_if(evaluate, location.clone())
+            }
+            "else" => conditions.push_else(&location),
+            _ => conditions.pop(&location),
+        }
+    }
+
+    /// Gets a human-readable location of the token,
+    /// including the current file if known.
+    fn location(&self, token: Token) -> String {
+        match self.file_stack.last() {
+            Some(file) => format!("{}:{}", file.display(), token.span),
+            None => token.span.to_string(),
+        }
+    }
+
+    /// Replaces a `<% @command args %>` directive.
+    fn replace_command(
+        &mut self,
+        name: &str,
+        args: &str,
+        token: Token,
+        source: &str,
+    ) -> Result<()> {
+        match name {
+            "toc" => {
+                let options = options::parse_or_default(args, &TocOptions::default());
+                self.renderer.render_toc(options)?;
+            }
+            "include" => self.replace_include(args, token)?,
+            "var" => self.replace_variable(args, token, source)?,
+            "each" => self.replace_each(args, token, source)?,
+            _ => {
+                warn!("Unknown directive '@{name}' at {}", self.location(token));
+                self.renderer.render_text(source)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces a `<% @include "path" { options } %>` directive
+    /// with the processed contents of the file.
+    ///
+    /// The path is relative to the file containing the directive.
+    fn replace_include(&mut self, args: &str, token: Token) -> Result<()> {
+        let (path, opts) = args
+            .strip_prefix('"')
+            .and_then(|args| args.split_once('"'))
+            .ok_or_else(|| {
+                eyre!(
+                    "Expected quoted path for include at {}",
+                    self.location(token)
+                )
+            })?;
+
+        let options = options::parse_or_default(opts, &IncludeOptions::default());
+
+        let path = match self.file_stack.last().and_then(|file| file.parent()) {
+            Some(parent) => parent.join(path),
+            None => PathBuf::from(path),
+        };
+
+        debug!("Including file: {}", path.display());
+
+        self.depth_offset += options.depth;
+        let res = self
+            .replace_file(&path)
+            .wrap_err_with(|| format!("Failed to include file at {}", self.location(token)));
+        self.depth_offset -= options.depth;
+
+        res
+    }
+
+    /// Replaces a `<% @each module::* { options } %>` directive
+    /// with every matching element in the module.
+    fn replace_each(&mut self, args: &str, token: Token, source: &str) -> Result<()> {
+        let (pattern, opts) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+
+        let mut each =
+            options::parse_or_default(opts, &EachOptions::new(self.context.defaults.clone()));
+        each.options.depth += self.depth_offset;
+
+        let name = each
+            .name
+            .as_deref()
+            .map(|name| Glob::new(name).map(|glob| glob.compile_matcher()))
+            .transpose()
+            .wrap_err_with(|| format!("Invalid name pattern at {}", self.location(token)))?;
+
+        let Some(elements) = self.context.resolver.resolve_each(pattern) else {
+            warn!("Could not resolve '{pattern}' at {}", self.location(token));
+            self.renderer.render_text(source)?;
+            return Ok(());
+        };
+
+        let elements = elements.into_iter().filter(|info| {
+            each.includes_element(info)
+                && name
+                    .as_ref()
+                    .is_none_or(|name| name.is_match(&info.ident) || name.is_match(&info.name))
+        });
+
+        for info in elements {
+            self.render(Resolved::Element(info), each.options.clone())
+                .wrap_err_with(|| {
+                    format!(
+                        "Failed to render '{}' at {}",
+                        info.name,
+                        self.location(token)
+                    )
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces a `<% @var name %>` directive with the variable's value.
+    fn replace_variable(&mut self, name: &str, token: Token, source: &str) -> Result<()> {
+        match self.context.variables.get(name) {
+            Some(value) => self.renderer.render_text(value)?,
+            None if self.context.strict => {
+                return Err(eyre!(
+                    "Unknown variable '{name}' at {}",
+                    self.location(token)
+                ));
+            }
+            None => {
+                warn!("Unknown variable '{name}' at {}", self.location(token));
+                self.renderer.render_text(source)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces a `<% path { options } %>` directive with the resolved element.
+    fn replace_injection(&mut self, directive: &str, token: Token, source: &str) -> Result<()> {
+        let (path, mut opts) = directive
+            .split_once(' ')
+            .map(|(path, opts)| (path, options::parse_or_default(opts, self.context.defaults)))
+            .unwrap_or_else(|| (directive, self.context.defaults.clone()));
+
+        opts.depth += self.depth_offset;
+
+        if let Some(resolved) = self.context.resolver.resolve_path(path) {
+            self.render(resolved, opts).wrap_err_with(|| {
+                format!("Failed to render '{path}' at {}", self.location(token))
+            })?;
+        } else {
+            warn!("Could not resolve '{path}' at {}", self.location(token));
+            self.renderer.render_text(source)?;
+        };
+
+        Ok(())
+    }
+
+    /// Renders the element using a custom template if there is one,
+    /// otherwise using the renderer's built-in layout.
+    pub fn render(&mut self, resolved: Resolved<'a>, options: ReplaceOptions) -> Result<()> {
+        self.injected.push((resolved, options.depth));
+
+        if options.inline {
+            return self.render_inline(resolved);
+        }
+
+        if let Some(scope) = self.context.resolver.owner(resolved) {
+            self.renderer.set_scope(scope);
+        }
+
+        let templates = self.context.templates;
+
+        match templates.render(
+            resolved,
+            &options,
+            self.renderer.options(),
+            self.context.resolver,
+        )? {
+            Some(output) => {
+                // templates are expected to start with a heading when headers are enabled
+                if options.header {
+                    self.renderer.render_target(resolved)?;
+                }
+                self.renderer.render_block(&output)?
+            }
+            None => self.renderer.render_resolved(resolved, options)?,
+        }
+
+        Ok(())
+    }
+
+    /// Renders just the value of a constant or static.
+    fn render_inline(&mut self, resolved: Resolved<'a>) -> Result<()> {
+        let value = match resolved {
+            Resolved::Element(Info {
+                element: element @ (ElementInfo::Const(_) | ElementInfo::Static(_)),
+                ..
+            }) => element
+                .value()
+                .ok_or_else(|| eyre!("Value is not a literal, so cannot be rendered inline"))?,
+            _ => return Err(eyre!("Only constants and statics can be rendered inline")),
+        };
+
+        self.renderer.render_text(value)?;
+        Ok(())
+    }
+
+    /// Gets every element, field and variant rendered so far,
+    /// with the heading depth each was rendered at,
+    /// in the order they were rendered.
+    pub fn injected(&self) -> &[(Resolved<'a>, usize)] {
+        &self.injected
+    }
+
+    pub(crate) fn finish(self) -> W {
+        self.renderer.finish()
+    }
+}
+
+/// Splits a `@command args` directive into the command name and its arguments.
+/// Returns `None` if the directive is not a command.
+fn parse_command(directive: &str) -> Option<(&str, &str)> {
+    let command = directive.trim().strip_prefix('@')?;
+    let (name, args) = command
+        .split_once(char::is_whitespace)
+        .unwrap_or((command, ""));
+
+    Some((name, args.trim()))
+}
+
+/// Increases the level of a Markdown ATX heading by `shift`,
+/// up to the maximum level of 6.
+/// Any other text is returned unchanged.
+fn shift_heading(line: &str, shift: usize) -> Cow<'_, str> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let is_heading = (1..=6).contains(&level)
+        && (level == line.len() || line[level..].starts_with([' ', '\t', '\n']));
+
+    if shift == 0 || !is_heading {
+        return Cow::Borrowed(line);
+    }
+
+    let new_level = (level + shift).min(6);
+    Cow::Owned(format!("{}{}", "#".repeat(new_level), &line[level..]))
+}
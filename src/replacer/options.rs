@@ -0,0 +1,302 @@
+use color_eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::error;
+
+use crate::{FieldInfo, Info, VariantInfo, Visibility};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReplaceOptions {
+    /// Whether to include the element header.
+    /// Defaults to true.
+    #[serde(default = "default_true")]
+    pub header: bool,
+
+    /// The current heading depth, starting at `0`.
+    /// Headings will be placed at one more than the current depth.
+    /// For example, if the next heading should be `## h2`, use a depth of `1`.
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+
+    /// Names of the only fields or variants to include.
+    /// If empty, all are included.
+    #[serde(default)]
+    pub only: Vec<String>,
+
+    /// Names of fields or variants to leave out.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Whether to only include fields marked `pub` or `pub(crate)`.
+    /// Defaults to false.
+    #[serde(default)]
+    pub public_only: bool,
+
+    /// Whether to only include fields and variants which have a doc comment.
+    /// Defaults to false.
+    #[serde(default)]
+    pub documented_only: bool,
+
+    /// How to lay out struct fields.
+    /// Defaults to `headings`.
+    #[serde(default)]
+    pub layout: Layout,
+
+    /// When using the table layout, whether to add a details section below the table
+    /// containing the rest of each field's description.
+    /// Defaults to false.
+    #[serde(default)]
+    pub details: bool,
+
+    /// The order to render fields and variants in.
+    /// Defaults to `source`.
+    #[serde(default)]
+    pub sort: SortOrder,
+
+    /// Whether to render fields and variants with a `group` set
+    /// under a sub-heading for each group.
+    /// Defaults to true.
+    #[serde(default = "default_true")]
+    pub group: bool,
+
+    /// Name of the set of custom templates to render with,
+    /// ie the sub-directory of the templates directory.
+    /// If not set, templates in the root of the templates directory are used if present.
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Whether to output just the value of a constant or static,
+    /// for use inside a sentence.
+    /// Defaults to false.
+    #[serde(default)]
+    pub inline: bool,
+}
+
+/// The order fields and variants are rendered in.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+    /// The order they are declared in the code.
+    #[default]
+    Source,
+    /// Alphabetical order of their displayed names.
+    Name,
+    /// Required fields first, then the rest, each in source order.
+    RequiredFirst,
+}
+
+/// How struct fields are laid out.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Each field is rendered under its own heading.
+    #[default]
+    Headings,
+    /// Fields are rendered as rows in a single table,
+    /// with only the first paragraph of each description.
+    Table,
+}
+
+impl Default for ReplaceOptions {
+    fn default() -> Self {
+        Self {
+            header: true,
+            depth: 1,
+            only: vec![],
+            exclude: vec![],
+            public_only: false,
+            documented_only: false,
+            layout: Layout::default(),
+            details: false,
+            sort: SortOrder::default(),
+            group: true,
+            template: None,
+            inline: false,
+        }
+    }
+}
+
+impl ReplaceOptions {
+    /// Checks whether the field passes the filters.
+    pub fn includes_field(&self, field: &FieldInfo) -> bool {
+        self.includes_name(&field.ident, &field.name)
+            && (!self.public_only
+                || matches!(field.visibility, Visibility::Public | Visibility::Crate))
+            && (!self.documented_only || !field.description.trim().is_empty())
+    }
+
+    /// Checks whether the variant passes the filters.
+    pub fn includes_variant(&self, variant: &VariantInfo) -> bool {
+        self.includes_name(&variant.ident, &variant.name)
+            && (!self.documented_only || !variant.description.trim().is_empty())
+    }
+
+    /// Checks the `only` and `exclude` lists against
+    /// both the source name and renamed name.
+    fn includes_name(&self, ident: &str, name: &str) -> bool {
+        let matches = |names: &[String]| names.iter().any(|n| n == ident || n == name);
+        (self.only.is_empty() || matches(&self.only)) && !matches(&self.exclude)
+    }
+}
+
+/// Attempts to parse the string using `Corn`,
+/// applying any set options over the top of `defaults`.
+///
+/// If invalid, the error is logged
+/// and the default options are returned instead.
+pub fn parse_or_default<T>(str: &str, defaults: &T) -> T
+where
+    T: Serialize + DeserializeOwned + Clone,
+{
+    if str.trim().is_empty() {
+        return defaults.clone();
+    }
+
+    parse_options(str, defaults).unwrap_or_else(|err| {
+        error!("Invalid options:\n{err}");
+        defaults.clone()
+    })
+}
+
+/// Parses the string using `Corn`,
+/// applying any set options over the top of `defaults`.
+pub fn parse_options<T>(str: &str, defaults: &T) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let mut options = serde_json::to_value(defaults)?;
+    let overrides = serde_json::to_value(libcorn::parse(str)?)?;
+
+    merge_values(&mut options, overrides);
+
+    Ok(serde_json::from_value(options)?)
+}
+
+/// Recursively merges `overrides` into `base`.
+/// Objects are merged key by key, any other value is replaced.
+fn merge_values(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+const fn default_depth() -> usize {
+    1
+}
+
+/// Options for a table of contents.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct TocOptions {
+    /// The highest level of heading to include,
+    /// where `1` is `# h1`.
+    /// Defaults to `2`.
+    #[serde(default = "default_toc_min_level")]
+    pub min_level: usize,
+
+    /// The lowest level of heading to include.
+    /// Defaults to `3`.
+    #[serde(default = "default_toc_max_level")]
+    pub max_level: usize,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        Self {
+            min_level: default_toc_min_level(),
+            max_level: default_toc_max_level(),
+        }
+    }
+}
+
+const fn default_toc_min_level() -> usize {
+    2
+}
+
+const fn default_toc_max_level() -> usize {
+    3
+}
+
+/// Options for an included file.
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy)]
+pub struct IncludeOptions {
+    /// Number of levels to shift headings in the included file down by,
+    /// including those of any injected elements.
+    /// For example, with a depth of `1`, `# h1` becomes `## h2`.
+    /// Defaults to `0`.
+    #[serde(default)]
+    pub depth: usize,
+}
+
+/// Options for injecting each element in a module.
+///
+/// Any injection options can also be set,
+/// which are applied to every element.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EachOptions {
+    /// Glob pattern the element name must match, for example `*Config`.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Name of a trait the element must implement, for example `Widget`.
+    /// This does not include derived traits.
+    #[serde(default)]
+    pub implements: Option<String>,
+
+    /// Name of a trait the element must derive, for example `Deserialize`.
+    #[serde(default)]
+    pub derives: Option<String>,
+
+    /// Kind of element to include:
+    /// `struct`, `enum`, `alias`, `const` or `static`.
+    /// Defaults to both structs and enums.
+    #[serde(default)]
+    pub kind: Option<String>,
+
+    #[serde(flatten)]
+    pub options: ReplaceOptions,
+}
+
+impl EachOptions {
+    pub fn new(options: ReplaceOptions) -> Self {
+        Self {
+            name: None,
+            implements: None,
+            derives: None,
+            kind: None,
+            options,
+        }
+    }
+
+    /// Checks whether the element passes the `kind`, `implements` and `derives` filters.
+    /// The name is checked separately, as it requires compiling the glob.
+    pub fn includes_element(&self, info: &Info) -> bool {
+        let has = |filter: &Option<String>, traits: &[String]| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| traits.iter().any(|t| t == filter))
+        };
+
+        let kind = info.element.kind();
+        let is_kind = match &self.kind {
+            Some(filter) => filter == kind,
+            None => kind == "struct" || kind == "enum",
+        };
+
+        is_kind && has(&self.implements, &info.implements) && has(&self.derives, &info.derives)
+    }
+}
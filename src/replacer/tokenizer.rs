@@ -0,0 +1,339 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Keyword which can be added to a fenced code block's info string
+/// to process directives inside it.
+const FENCE_KEYWORD: &str = "bindocs";
+
+const DIRECTIVE_START: &str = "<%";
+const DIRECTIVE_END: &str = "%>";
+const ESCAPE: &str = "<%%";
+
+/// A location in the template source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Byte offset of the start of the span.
+    pub start: usize,
+    /// Byte offset of the end of the span (exclusive).
+    pub end: usize,
+    /// Line number of the start of the span, starting at `1`.
+    pub line: usize,
+    /// Column number of the start of the span, in characters, starting at `1`.
+    pub column: usize,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Static text, which should be output as-is.
+    Text,
+    /// Static text inside inline code or a fenced code block,
+    /// including the fences themselves, which should be output as-is.
+    Code,
+    /// The contents of a `<% ... %>` block.
+    Directive,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    /// For text, the text to output.
+    /// For directives, the contents between the delimiters.
+    pub text: &'a str,
+    /// Location of the full token in the source, including any delimiters.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizeError {
+    /// A `<%` was found with no matching `%>`.
+    UnterminatedDirective(Span),
+}
+
+impl Display for TokenizeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnterminatedDirective(span) => {
+                write!(f, "Unterminated directive starting at {span}")
+            }
+        }
+    }
+}
+
+impl Error for TokenizeError {}
+
+/// An open fenced code block.
+struct Fence {
+    marker: u8,
+    len: usize,
+    /// Whether directives inside the block should be processed.
+    process: bool,
+}
+
+/// Splits a template into static text and directives in a single pass.
+///
+/// When `skip_code` is set, directives inside Markdown inline code
+/// and fenced code blocks are treated as text.
+pub struct Tokenizer<'a> {
+    input: &'a str,
+    skip_code: bool,
+
+    pos: usize,
+    line: usize,
+    column: usize,
+
+    fence: Option<Fence>,
+    pending: VecDeque<Token<'a>>,
+    failed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(input: &'a str, skip_code: bool) -> Self {
+        Self {
+            input,
+            skip_code,
+            pos: 0,
+            line: 1,
+            column: 1,
+            fence: None,
+            pending: VecDeque::new(),
+            failed: false,
+        }
+    }
+
+    fn is_line_start(&self) -> bool {
+        self.column == 1
+    }
+
+    /// Gets the span starting at the current position with the given length in bytes,
+    /// and moves the position to the end of it.
+    fn advance(&mut self, len: usize) -> Span {
+        let start = self.pos;
+        let end = start + len;
+
+        let span = Span {
+            start,
+            end,
+            line: self.line,
+            column: self.column,
+        };
+
+        for c in self.input[start..end].chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        self.pos = end;
+        span
+    }
+
+    fn text(&mut self, len: usize) -> Token<'a> {
+        self.static_token(TokenKind::Text, len)
+    }
+
+    fn code(&mut self, len: usize) -> Token<'a> {
+        self.static_token(TokenKind::Code, len)
+    }
+
+    fn static_token(&mut self, kind: TokenKind, len: usize) -> Token<'a> {
+        let span = self.advance(len);
+        Token {
+            kind,
+            text: &self.input[span.start..span.end],
+            span,
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token<'a>, TokenizeError> {
+        let rest = &self.input[self.pos..];
+        let processing = self.fence.as_ref().is_none_or(|fence| fence.process);
+
+        if self.skip_code && self.is_line_start() && is_fence(rest) {
+            Ok(self.fence_line())
+        } else if processing && rest.starts_with(ESCAPE) {
+            let mut token = self.text(ESCAPE.len());
+            token.text = &token.text[..DIRECTIVE_START.len()];
+            Ok(token)
+        } else if processing && rest.starts_with(DIRECTIVE_START) {
+            self.directive()
+        } else if self.skip_code && self.fence.is_none() && rest.starts_with('`') {
+            Ok(self.inline_code())
+        } else {
+            Ok(self.static_text(processing))
+        }
+    }
+
+    fn directive(&mut self) -> Result<Token<'a>, TokenizeError> {
+        let rest = &self.input[self.pos..];
+
+        match rest[DIRECTIVE_START.len()..].find(DIRECTIVE_END) {
+            Some(end) => {
+                let len = DIRECTIVE_START.len() + end + DIRECTIVE_END.len();
+                let span = self.advance(len);
+
+                Ok(Token {
+                    kind: TokenKind::Directive,
+                    text: &rest[DIRECTIVE_START.len()..DIRECTIVE_START.len() + end],
+                    span,
+                })
+            }
+            None => Err(TokenizeError::UnterminatedDirective(
+                self.advance(rest.len()),
+            )),
+        }
+    }
+
+    /// Parses a line opening or closing a fenced code block.
+    ///
+    /// If an opening fence's info string includes the `bindocs` keyword,
+    /// directives inside the block are processed and the keyword is removed.
+    fn fence_line(&mut self) -> Token<'a> {
+        let rest = &self.input[self.pos..];
+        let line = rest.find('\n').map_or(rest, |end| &rest[..end]);
+
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        let marker = trimmed.as_bytes()[0];
+        let marker_len = trimmed.bytes().take_while(|&c| c == marker).count();
+        let info = &trimmed[marker_len..];
+
+        match &self.fence {
+            Some(fence)
+                if fence.marker == marker && marker_len >= fence.len && info.trim().is_empty() =>
+            {
+                self.fence = None;
+            }
+            Some(_) => {}
+            None => {
+                let keyword =
+                    find_word(info, FENCE_KEYWORD).map(|start| indent + marker_len + start);
+
+                self.fence = Some(Fence {
+                    marker,
+                    len: marker_len,
+                    process: keyword.is_some(),
+                });
+
+                if let Some(keyword_start) = keyword {
+                    // output the line either side of the keyword and its leading whitespace
+                    let before = line[..keyword_start].trim_end().len();
+                    let after = keyword_start + FENCE_KEYWORD.len();
+
+                    let token = self.code(before);
+                    self.advance(after - before);
+                    if after < line.len() {
+                        let remaining = self.code(line.len() - after);
+                        self.pending.push_back(remaining);
+                    }
+
+                    return token;
+                }
+            }
+        }
+
+        self.code(line.len())
+    }
+
+    /// Parses an inline code span, which is output verbatim.
+    /// If the span is not closed within the paragraph,
+    /// only the opening backticks are consumed.
+    fn inline_code(&mut self) -> Token<'a> {
+        let rest = &self.input.as_bytes()[self.pos..];
+        let ticks = rest.iter().take_while(|&&c| c == b'`').count();
+
+        let mut index = ticks;
+        while index < rest.len() {
+            if rest[index..].starts_with(b"\n\n") {
+                break;
+            } else if rest[index] == b'`' {
+                let run = rest[index..].iter().take_while(|&&c| c == b'`').count();
+                if run == ticks {
+                    return self.code(index + run);
+                }
+
+                index += run;
+            } else {
+                index += 1;
+            }
+        }
+
+        self.text(ticks)
+    }
+
+    /// Parses a run of static text, up to the next position which could start
+    /// a directive, or a code span or fence when skipping code.
+    fn static_text(&mut self, processing: bool) -> Token<'a> {
+        let rest = &self.input.as_bytes()[self.pos..];
+
+        let len = (1..rest.len())
+            .find(|&i| {
+                (processing && rest[i..].starts_with(DIRECTIVE_START.as_bytes()))
+                    || (self.skip_code && (rest[i] == b'`' || rest[i - 1] == b'\n'))
+            })
+            .unwrap_or(rest.len());
+
+        if self.fence.is_some() {
+            self.code(len)
+        } else {
+            self.text(len)
+        }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(Ok(token));
+        }
+
+        if self.failed || self.pos >= self.input.len() {
+            return None;
+        }
+
+        let token = self.next_token();
+        self.failed = token.is_err();
+
+        Some(token)
+    }
+}
+
+/// Checks whether the line starts a fenced code block,
+/// ie three or more backticks or tildes, indented by no more than three spaces.
+fn is_fence(line: &str) -> bool {
+    let indent = line.bytes().take_while(|&c| c == b' ').count();
+    if indent > 3 {
+        return false;
+    }
+
+    match line.as_bytes().get(indent) {
+        Some(&marker @ (b'`' | b'~')) => {
+            line[indent..].bytes().take_while(|&c| c == marker).count() >= 3
+        }
+        _ => false,
+    }
+}
+
+/// Finds the byte offset of `word` in `str`,
+/// where it is surrounded by whitespace or the start/end of the string.
+fn find_word(str: &str, word: &str) -> Option<usize> {
+    str.match_indices(word)
+        .map(|(start, _)| start)
+        .find(|&start| {
+            let before = str[..start].chars().next_back();
+            let after = str[start + word.len()..].chars().next();
+
+            before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+        })
+}
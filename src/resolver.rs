@@ -1,28 +1,74 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::ptr;
 
 use color_eyre::Result;
-use syn::Item;
+use globset::GlobSet;
+use syn::{Attribute, Ident, Item, ItemImpl, Type};
+use tracing::debug;
 
 use crate::module_path::ModulePath;
-use crate::parser::extract_doc_comment;
-use crate::utils::PathExt;
-use crate::{parser, ElementInfo, FileInfo, Info};
+use crate::parser::{
+    extract_doc_comment, get_location, is_enabled, parse_attributes, parse_derives,
+    should_document, ParseContext,
+};
+use crate::utils::{build_glob_set, PathExt};
+use crate::{parser, ElementInfo, FieldInfo, FileInfo, Import, Info, TypeInfo, VariantInfo};
 
 pub type ModuleCache = HashMap<ModulePath, FileInfo>;
 
+/// Options controlling which source code is read.
+#[derive(Debug, Default)]
+pub struct ResolveOptions {
+    /// Crate features to treat as enabled.
+    pub features: Vec<String>,
+    /// Glob patterns of source files to include, relative to the `src` folder.
+    pub include: Vec<String>,
+    /// Glob patterns of source files to exclude, relative to the `src` folder.
+    pub exclude: Vec<String>,
+}
+
+/// An element, or a single part of one, found by the resolver.
+#[derive(Debug, Clone, Copy)]
+pub enum Resolved<'a> {
+    Element(&'a Info),
+    Field(&'a FieldInfo),
+    Variant(&'a VariantInfo),
+}
+
+impl Resolved<'_> {
+    /// Gets a key identifying the item,
+    /// so the same item can be recognised when it is resolved from different paths.
+    pub fn key(self) -> usize {
+        match self {
+            Resolved::Element(info) => ptr::from_ref(info).addr(),
+            Resolved::Field(info) => ptr::from_ref(info).addr(),
+            Resolved::Variant(info) => ptr::from_ref(info).addr(),
+        }
+    }
+}
+
 pub struct Resolver {
     /// Path to the entry file (`main.rs` or `lib.rs`)
     entry_file: String,
     /// Path to the project `src` folder
     entry_path: PathBuf,
+    /// Absolute path to the crate root
+    manifest_dir: PathBuf,
     /// All resolved modules
     module_cache: ModuleCache,
+    /// Enabled crate features
+    features: Vec<String>,
+    /// Source files to read elements from.
+    /// If `None`, all files are included.
+    include: Option<GlobSet>,
+    /// Source files to skip reading elements from
+    exclude: GlobSet,
 }
 
 impl Resolver {
-    pub fn new<P: AsRef<Path>>(entry_file: P) -> Self {
+    pub fn new<P: AsRef<Path>>(entry_file: P, options: ResolveOptions) -> Result<Self> {
         let entry_path = entry_file
             .as_ref()
             .parent()
@@ -37,18 +83,71 @@ impl Resolver {
             .expect("to be valid string")
             .to_string();
 
-        Self {
+        let manifest_dir = std::path::absolute(entry_path.parent().unwrap_or(&entry_path))?;
+
+        let include = if options.include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(&options.include)?)
+        };
+
+        Ok(Self {
             entry_file,
             entry_path,
+            manifest_dir,
             module_cache: HashMap::new(),
-        }
+            features: options.features,
+            include,
+            exclude: build_glob_set(&options.exclude)?,
+        })
+    }
+
+    /// Gets every resolved module.
+    pub fn modules(&self) -> &ModuleCache {
+        &self.module_cache
+    }
+
+    /// Gets the crate features treated as enabled.
+    pub fn features(&self) -> &[String] {
+        &self.features
     }
 
     pub fn resolve(&mut self) -> Result<()> {
-        self.resolve_module(ModulePath::new())
+        let mut impls = vec![];
+        self.resolve_module(ModulePath::new(), &mut impls)?;
+
+        // impls can be in any module, and refer to types through its imports,
+        // so are only matched up once everything is resolved.
+        let mut implements = HashMap::<ModulePath, Vec<String>>::new();
+        for (module, ty, trait_name) in impls {
+            let path = ty.split("::").collect::<Vec<_>>();
+            if let Some(info) = self.resolve_scoped(&module, &path, 0) {
+                let key = info.module.join(&info.ident);
+                implements.entry(key).or_default().push(trait_name);
+            }
+        }
+
+        for element in self
+            .module_cache
+            .values_mut()
+            .flat_map(|file| file.elements.iter_mut())
+        {
+            if let Some(traits) = implements.get(&element.module.join(&element.ident)) {
+                element.implements.clone_from(traits);
+            }
+        }
+
+        Ok(())
     }
 
-    fn resolve_module(&mut self, module_path: ModulePath) -> Result<()> {
+    /// Reads the module and any modules it declares into the cache.
+    /// Trait impls found are added to `impls`,
+    /// with the module they are in, the type as written and the trait name.
+    fn resolve_module(
+        &mut self,
+        module_path: ModulePath,
+        impls: &mut Vec<(ModulePath, String, String)>,
+    ) -> Result<()> {
         let path = module_path.as_path(&self.entry_path, &self.entry_file);
 
         let file_name = path
@@ -59,14 +158,24 @@ impl Resolver {
             .to_string();
 
         let file = read_file(&path)?;
-        let items = Self::get_module_items(file.items, &module_path)?;
+        let mut items = self.get_module_items(file.items, &module_path, &path)?;
+
+        for (ty, trait_name) in items.impls {
+            impls.push((module_path.clone(), ty, trait_name));
+        }
+
+        if !self.is_included(&path) {
+            debug!("Skipping elements in excluded file: {}", path.display());
+            items.elements.clear();
+        }
 
         let info = {
             FileInfo {
                 // private fields kept in case they're needed
                 _name: file_name,
-                _path: path,
+                path,
                 elements: items.elements,
+                imports: items.imports,
             }
         };
 
@@ -74,41 +183,119 @@ impl Resolver {
 
         for import in items.modules {
             if !self.module_cache.contains_key(&import) {
-                self.resolve_module(import)?;
+                self.resolve_module(import, impls)?;
             }
         }
 
         Ok(())
     }
 
-    fn get_module_items(items: Vec<Item>, module_path: &ModulePath) -> Result<ModuleItems> {
+    /// Checks the source file against the include and exclude globs.
+    fn is_included(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.entry_path).unwrap_or(path);
+
+        let included = self
+            .include
+            .as_ref()
+            .is_none_or(|include| include.is_match(relative));
+
+        included && !self.exclude.is_match(relative)
+    }
+
+    fn get_module_items(
+        &self,
+        items: Vec<Item>,
+        module_path: &ModulePath,
+        file: &Path,
+    ) -> Result<ModuleItems> {
+        let context = ParseContext {
+            features: &self.features,
+            module: module_path,
+            file,
+            manifest_dir: &self.manifest_dir,
+        };
+
         let mut modules = vec![];
         let mut elements = vec![];
+        let mut impls = vec![];
+        let mut imports = vec![];
 
         for item in items {
             match item {
+                Item::Mod(module) if !is_enabled(&module.attrs, &self.features) => {}
+                Item::Enum(item_enum) if !should_document(&item_enum.attrs, &self.features) => {}
+                Item::Struct(item_struct)
+                    if !should_document(&item_struct.attrs, &self.features) => {}
+                Item::Type(item_type) if !should_document(&item_type.attrs, &self.features) => {}
+                Item::Const(item_const)
+                    if item_const.ident == "_"
+                        || !should_document(&item_const.attrs, &self.features) => {}
+                Item::Static(item_static)
+                    if !should_document(&item_static.attrs, &self.features) => {}
                 Item::Mod(module) => {
                     if let Some((_, items)) = module.content {
-                        Self::get_module_items(items, module_path)?;
+                        self.get_module_items(items, module_path, file)?;
                     } else {
                         modules.push(module_path.join(module.ident.to_string()));
                     }
                 }
-                Item::Enum(item_enum) => elements.push(Info {
-                    name: item_enum.ident.to_string(),
-                    description: extract_doc_comment(&item_enum.attrs),
-                    element: ElementInfo::Enum(parser::parse_enum(item_enum)),
-                }),
-                Item::Struct(item_struct) => elements.push(Info {
-                    name: item_struct.ident.to_string(),
-                    description: extract_doc_comment(&item_struct.attrs),
-                    element: ElementInfo::Struct(parser::parse_struct(item_struct)),
-                }),
+                Item::Enum(item_enum) => {
+                    let (ident, attrs) = (item_enum.ident.clone(), item_enum.attrs.clone());
+                    let element = ElementInfo::Enum(parser::parse_enum(item_enum, &context));
+                    elements.push(element_info(&ident, &attrs, element, &context));
+                }
+                Item::Struct(item_struct) => {
+                    let (ident, attrs) = (item_struct.ident.clone(), item_struct.attrs.clone());
+                    let element = ElementInfo::Struct(parser::parse_struct(item_struct, &context));
+                    elements.push(element_info(&ident, &attrs, element, &context));
+                }
+                Item::Type(item_type) => {
+                    let (ident, attrs) = (item_type.ident.clone(), item_type.attrs.clone());
+                    let element = ElementInfo::Alias(parser::parse_alias(item_type, &context));
+                    elements.push(element_info(&ident, &attrs, element, &context));
+                }
+                Item::Const(item_const) => {
+                    let element = ElementInfo::Const(parser::parse_const(
+                        item_const.ty,
+                        &item_const.expr,
+                        &context,
+                    ));
+                    elements.push(element_info(
+                        &item_const.ident,
+                        &item_const.attrs,
+                        element,
+                        &context,
+                    ));
+                }
+                Item::Static(item_static) => {
+                    let element = ElementInfo::Static(parser::parse_const(
+                        item_static.ty,
+                        &item_static.expr,
+                        &context,
+                    ));
+                    elements.push(element_info(
+                        &item_static.ident,
+                        &item_static.attrs,
+                        element,
+                        &context,
+                    ));
+                }
+                Item::Impl(item_impl) if is_enabled(&item_impl.attrs, &self.features) => {
+                    impls.extend(get_trait_impl(&item_impl));
+                }
+                Item::Use(item_use) if is_enabled(&item_use.attrs, &self.features) => {
+                    imports.extend(parser::parse_imports(&item_use));
+                }
                 _ => {}
             }
         }
 
-        Ok(ModuleItems { modules, elements })
+        Ok(ModuleItems {
+            modules,
+            elements,
+            impls,
+            imports,
+        })
     }
 
     pub fn resolve_absolute(&self, path: &ModulePath) -> Option<&Info> {
@@ -118,17 +305,234 @@ impl Resolver {
         if let Some(element) = element {
             self.module_cache
                 .get(&parent)
-                .and_then(|file| file.elements.iter().find(|el| el.name == element))
+                .and_then(|file| file.elements.iter().find(|el| el.ident == element))
         } else {
             None
         }
     }
 
+    /// Resolves an absolute or shorthand path.
+    ///
+    /// As well as elements, this can resolve enum variants (`Mode::Auto`)
+    /// and struct or variant fields (`config::Config.log_level`).
+    pub fn resolve_path(&self, path: &str) -> Option<Resolved<'_>> {
+        if let Some((parent, field)) = path.rsplit_once('.') {
+            let fields = match self.resolve_path(parent)? {
+                Resolved::Element(Info {
+                    element: ElementInfo::Struct(info),
+                    ..
+                }) => &info.fields,
+                Resolved::Variant(variant) => &variant.fields,
+                _ => return None,
+            };
+
+            return fields
+                .iter()
+                .find(|f| f.ident == field || f.name == field)
+                .map(Resolved::Field);
+        }
+
+        if let Some(info) = self.resolve_element(path) {
+            return Some(Resolved::Element(info));
+        }
+
+        let (parent, variant) = path.rsplit_once("::")?;
+        match self.resolve_element(parent)?.element {
+            ElementInfo::Enum(ref info) => info
+                .variants
+                .iter()
+                .find(|v| v.ident == variant || v.name == variant)
+                .map(Resolved::Variant),
+            _ => None,
+        }
+    }
+
+    /// Resolves the target of an intra-doc link
+    /// written in the doc comments of `scope`, or of one of its fields or variants.
+    ///
+    /// As in rustdoc, paths are relative to the scope's module
+    /// and `Self` refers to the scope itself.
+    /// Paths which are not found relative to the module are resolved as any other path,
+    /// and fields can be separated by `::` as well as `.`.
+    pub fn resolve_link(&self, target: &str, scope: Option<&Info>) -> Option<Resolved<'_>> {
+        let mut module = scope.map_or_else(ModulePath::new, |scope| scope.module.clone());
+
+        let self_path = target
+            .strip_prefix("Self")
+            .filter(|path| path.is_empty() || path.starts_with("::"));
+
+        let target = if let Some(path) = target.strip_prefix("crate::") {
+            module = ModulePath::new();
+            path.to_string()
+        } else if let Some(path) = self_path {
+            format!("{}{path}", scope?.ident)
+        } else {
+            target.to_string()
+        };
+
+        let mut target = target.as_str();
+        while let Some(path) = target.strip_prefix("super::") {
+            module = module.parent();
+            target = path;
+        }
+        let target = target.strip_prefix("self::").unwrap_or(target);
+
+        // paths relative to the module take precedence, as in rustdoc
+        self.resolve_link_path(&module.join(target).to_string())
+            .or_else(|| self.resolve_link_path(target))
+    }
+
+    /// Resolves an absolute or shorthand link target,
+    /// allowing fields to be separated by `::` as they are in rustdoc.
+    fn resolve_link_path(&self, target: &str) -> Option<Resolved<'_>> {
+        self.resolve_path(target).or_else(|| {
+            let (parent, field) = target.rsplit_once("::")?;
+            self.resolve_path(&format!("{parent}.{field}"))
+        })
+    }
+
+    /// Gets the element the item belongs to,
+    /// which for elements is the item itself.
+    pub fn owner(&self, resolved: Resolved) -> Option<&Info> {
+        let key = resolved.key();
+
+        self.module_cache
+            .values()
+            .flat_map(|file| &file.elements)
+            .find(|info| contains(info, key))
+    }
+
+    /// Resolves a type to the element in the crate it refers to,
+    /// following the imports of the module it is used in.
+    ///
+    /// Returns `None` for types from other crates,
+    /// and for names which are ambiguous between glob imports.
+    pub fn resolve_type(&self, ty: &TypeInfo) -> Option<&Info> {
+        let module = ty.module.as_ref()?;
+        let path = ty.name.split("::").collect::<Vec<_>>();
+
+        self.resolve_scoped(module, &path, 0)
+    }
+
+    /// Resolves a path to an element as written in the given module.
+    /// `depth` counts the imports followed, to stop at import cycles.
+    fn resolve_scoped(&self, module: &ModulePath, path: &[&str], depth: usize) -> Option<&Info> {
+        const MAX_DEPTH: usize = 16;
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let file = self.module_cache.get(module)?;
+        let import = |name: &str| {
+            file.imports
+                .iter()
+                .find(|import| import.name.as_deref() == Some(name))
+        };
+        let resolve_import = |import: &Import, rest: &[&str]| {
+            let path = import
+                .path
+                .iter()
+                .map(String::as_str)
+                .chain(rest.iter().copied())
+                .collect::<Vec<_>>();
+
+            self.resolve_scoped(module, &path, depth + 1)
+        };
+
+        match path {
+            [] => None,
+            ["crate", rest @ ..] => self.resolve_scoped(&ModulePath::new(), rest, depth),
+            ["self", rest @ ..] => self.resolve_scoped(module, rest, depth),
+            ["super", rest @ ..] => self.resolve_scoped(&module.parent(), rest, depth),
+            [name] => {
+                // items declared in the module shadow imports, which shadow glob imports
+                if let Some(info) = file.elements.iter().find(|el| el.ident == *name) {
+                    return Some(info);
+                }
+
+                if let Some(import) = import(name) {
+                    return resolve_import(import, &[]);
+                }
+
+                let found = file
+                    .imports
+                    .iter()
+                    .filter(|import| import.name.is_none())
+                    .filter_map(|import| resolve_import(import, path))
+                    .collect::<Vec<_>>();
+
+                match found[..] {
+                    [info] => Some(info),
+                    _ => None,
+                }
+            }
+            [first, rest @ ..] => {
+                let child = module.join(first);
+                if self.module_cache.contains_key(&child) {
+                    return self.resolve_scoped(&child, rest, depth);
+                }
+
+                import(first).and_then(|import| resolve_import(import, rest))
+            }
+        }
+    }
+
+    /// Gets every element in the module matching the pattern,
+    /// in a consistent order.
+    ///
+    /// `path::*` matches the elements in the module in source order.
+    /// `path::**` also matches those in any submodules,
+    /// with modules ordered by their path.
+    /// `*` and `**` on their own match from the crate root.
+    ///
+    /// Returns `None` if the module does not exist.
+    pub fn resolve_each(&self, pattern: &str) -> Option<Vec<&Info>> {
+        let (module, recursive) = match pattern.rsplit_once("::") {
+            Some((module, "*")) => (module.into(), false),
+            Some((module, "**")) => (module.into(), true),
+            None if pattern == "*" => (ModulePath::new(), false),
+            None if pattern == "**" => (ModulePath::new(), true),
+            _ => return None,
+        };
+
+        if !self.module_cache.contains_key(&module) {
+            return None;
+        }
+
+        let mut modules = self
+            .module_cache
+            .iter()
+            .filter(|(path, _)| {
+                if recursive {
+                    path.starts_with(&module)
+                } else {
+                    **path == module
+                }
+            })
+            .collect::<Vec<_>>();
+
+        modules.sort_by_key(|(path, _)| *path);
+
+        Some(
+            modules
+                .into_iter()
+                .flat_map(|(_, file)| &file.elements)
+                .collect(),
+        )
+    }
+
+    /// Resolves an element by its absolute path,
+    /// falling back to a shorthand path.
+    pub fn resolve_element(&self, path: &str) -> Option<&Info> {
+        self.resolve_absolute(&path.into())
+            .or_else(|| self.resolve_shorthand(path))
+    }
+
     pub fn resolve_shorthand(&self, element: &str) -> Option<&Info> {
         let results = self
             .module_cache
-            .iter()
-            .filter_map(|(path, _)| self.resolve_absolute(&path.join(element)))
+            .keys()
+            .filter_map(|path| self.resolve_absolute(&path.join(element)))
             .collect::<Vec<_>>();
 
         if results.len() == 1 {
@@ -161,7 +565,75 @@ fn read_file<P: AsRef<Path>>(module: P) -> Result<syn::File> {
     Ok(tree)
 }
 
+/// Gets the information shared by every kind of element,
+/// from the item's name and attributes.
+fn element_info(
+    ident: &Ident,
+    attrs: &[Attribute],
+    element: ElementInfo,
+    context: &ParseContext,
+) -> Info {
+    let attributes = parse_attributes(attrs, context.features);
+
+    Info {
+        ident: ident.to_string(),
+        module: context.module.clone(),
+        location: get_location(ident.span()),
+        derives: parse_derives(attrs, context.features),
+        implements: vec![],
+        name: attributes.name.unwrap_or_else(|| ident.to_string()),
+        description: extract_doc_comment(attrs, context),
+        since: attributes.since,
+        element,
+    }
+}
+
+/// Gets the type path, as written, and trait name for a trait impl.
+/// Returns `None` for inherent and negative impls.
+fn get_trait_impl(item: &ItemImpl) -> Option<(String, String)> {
+    let (negative, trait_path, _) = item.trait_.as_ref()?;
+    if negative.is_some() {
+        return None;
+    }
+
+    let trait_name = trait_path.segments.last()?.ident.to_string();
+
+    let Type::Path(ty) = item.self_ty.as_ref() else {
+        return None;
+    };
+    let ty = ty
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+
+    Some((ty, trait_name))
+}
+
+/// Checks whether the element is, or contains, the item with the given key.
+fn contains(info: &Info, key: usize) -> bool {
+    let contains_field = |fields: &[FieldInfo]| {
+        fields
+            .iter()
+            .any(|field| Resolved::Field(field).key() == key)
+    };
+
+    Resolved::Element(info).key() == key
+        || match &info.element {
+            ElementInfo::Struct(element) => contains_field(&element.fields),
+            ElementInfo::Enum(element) => element.variants.iter().any(|variant| {
+                Resolved::Variant(variant).key() == key || contains_field(&variant.fields)
+            }),
+            ElementInfo::Alias(_) | ElementInfo::Const(_) | ElementInfo::Static(_) => false,
+        }
+}
+
 struct ModuleItems {
     modules: Vec<ModulePath>,
     elements: Vec<Info>,
+    /// Pairs of type path and trait name for each trait impl.
+    impls: Vec<(String, String)>,
+    imports: Vec<Import>,
 }
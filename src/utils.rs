@@ -1,3 +1,5 @@
+use color_eyre::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use std::path::{Path, PathBuf};
 
 pub trait PathExt<P: AsRef<Path>> {
@@ -17,3 +19,13 @@ where
         }
     }
 }
+
+/// Builds a set from a list of glob patterns.
+pub fn build_glob_set<S: AsRef<str>>(patterns: &[S]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern.as_ref())?);
+    }
+
+    Ok(builder.build()?)
+}
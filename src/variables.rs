@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use color_eyre::Result;
+use toml::{Table, Value};
+use tracing::debug;
+
+/// Prefix of environment variables exposed to templates.
+const ENV_PREFIX: &str = "BINDOCS_";
+
+/// Keys read from the manifest `[package]` table.
+const PACKAGE_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "description",
+    "repository",
+    "homepage",
+    "documentation",
+    "license",
+    "rust-version",
+    "edition",
+    "authors",
+    "keywords",
+    "categories",
+];
+
+/// Named values which can be output in templates using `<% @var name %>`.
+///
+/// These are made up of:
+///
+/// - `package.*` values from `Cargo.toml`, such as `package.version`.
+///   Hyphens in key names are replaced with underscores, ie `package.rust_version`.
+/// - `features`, a comma-separated list of the crate's features.
+/// - Any `variables` set in the config.
+/// - Any environment variables starting with `BINDOCS_`, as `env.*`.
+///   For example `BINDOCS_DOCS_URL` is available as `env.docs_url`.
+#[derive(Debug, Default)]
+pub struct Variables {
+    values: HashMap<String, String>,
+}
+
+impl Variables {
+    /// Loads the variables for the project at the given path.
+    /// Values from the config take precedence over those from the manifest.
+    pub fn load(project_path: &Path, config_variables: &HashMap<String, String>) -> Result<Self> {
+        let mut variables = Self::default();
+
+        let manifest_path = project_path.join("Cargo.toml");
+        if manifest_path.exists() {
+            let manifest = fs::read_to_string(&manifest_path)?.parse::<Table>()?;
+            let workspace = find_workspace(project_path, &manifest)?;
+            variables.load_manifest(&manifest, workspace.as_ref());
+        }
+
+        for (key, value) in env::vars() {
+            if let Some(name) = key.strip_prefix(ENV_PREFIX) {
+                variables.insert(format!("env.{}", name.to_lowercase()), value);
+            }
+        }
+
+        for (key, value) in config_variables {
+            variables.insert(key.clone(), value.clone());
+        }
+
+        debug!("Loaded {} template variables", variables.values.len());
+
+        Ok(variables)
+    }
+
+    fn load_manifest(&mut self, manifest: &Table, workspace: Option<&Value>) {
+        let workspace_package = workspace.and_then(|workspace| workspace.get("package"));
+
+        if let Some(package) = manifest.get("package") {
+            for &key in PACKAGE_KEYS {
+                let value = match package.get(key) {
+                    // `key.workspace = true` inherits the value from the workspace
+                    Some(Value::Table(table)) if table.contains_key("workspace") => {
+                        workspace_package.and_then(|package| package.get(key))
+                    }
+                    value => value,
+                };
+
+                if let Some(value) = value.and_then(to_text) {
+                    self.insert(format!("package.{}", key.replace('-', "_")), value);
+                }
+            }
+        }
+
+        if let Some(Value::Table(features)) = manifest.get("features") {
+            let features = features.keys().cloned().collect::<Vec<_>>();
+            self.insert("features".to_string(), features.join(", "));
+        }
+    }
+
+    fn insert(&mut self, name: String, value: String) {
+        self.values.insert(name, value);
+    }
+
+    /// Gets the value of the variable with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+}
+
+/// Gets the `[workspace]` table of the workspace the crate belongs to.
+/// This is in the crate's own manifest for the workspace root,
+/// otherwise in the first parent directory with a manifest containing one,
+/// unless the crate sets `package.workspace` to its path.
+fn find_workspace(project_path: &Path, manifest: &Table) -> Result<Option<Value>> {
+    if let Some(workspace) = manifest.get("workspace") {
+        return Ok(Some(workspace.clone()));
+    }
+
+    let project_path = project_path.canonicalize()?;
+
+    let explicit_path = manifest
+        .get("package")
+        .and_then(|package| package.get("workspace"))
+        .and_then(Value::as_str);
+
+    let candidates = match explicit_path {
+        Some(path) => vec![project_path.join(path)],
+        None => project_path
+            .ancestors()
+            .skip(1)
+            .map(Path::to_path_buf)
+            .collect(),
+    };
+
+    for dir in candidates {
+        let manifest_path = dir.join("Cargo.toml");
+        if !manifest_path.exists() {
+            continue;
+        }
+
+        let manifest = fs::read_to_string(&manifest_path)?.parse::<Table>()?;
+        if let Some(workspace) = manifest.get("workspace") {
+            debug!("Found workspace manifest: {}", manifest_path.display());
+            return Ok(Some(workspace.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Converts a manifest value into the text to output.
+/// Arrays are joined into a comma-separated list.
+fn to_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(str) => Some(str.clone()),
+        Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => Some(value.to_string()),
+        Value::Array(values) => Some(
+            values
+                .iter()
+                .filter_map(to_text)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        _ => None,
+    }
+}